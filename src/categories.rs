@@ -0,0 +1,413 @@
+use crate::config::{CategoryConfig, Config};
+use crate::error::{OrganizerError, Result};
+use crate::folders::FOLDERS_CATEGORY;
+use crate::metadata::ExifInfo;
+use crate::projects::PROJECTS_CATEGORY;
+use crate::rules::{Candidate, Rule};
+use crate::template::{Layout, RenamePattern, Template, MUSIC_TEMPLATE};
+use std::path::{Component, Path};
+
+const DEFAULT_FALLBACK: &str = "Misc";
+const MUSIC: &str = "Music";
+
+#[derive(Debug, Clone)]
+pub struct Category {
+    pub name: String,
+    pub folder: String,
+    mime: Vec<String>,
+    extensions: Vec<String>,
+    camera: Option<bool>,
+    gps: Option<bool>,
+    template: Option<Template>,
+}
+
+impl Category {
+    fn new(name: &str, mime: &[&str], extensions: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            folder: name.to_string(),
+            mime: mime.iter().map(|m| m.to_string()).collect(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            camera: None,
+            gps: None,
+            template: None,
+        }
+    }
+
+    fn from_config(config: &CategoryConfig) -> Result<Self> {
+        if config.name.trim().is_empty() {
+            return Err(OrganizerError::Config(
+                "category name cannot be empty".to_string(),
+            ));
+        }
+
+        let folder = config.folder.clone().unwrap_or_else(|| config.name.clone());
+        let mut components = Path::new(&folder).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(OrganizerError::Config(format!(
+                "category '{}' has invalid folder '{}' (must be a single directory name)",
+                config.name, folder
+            )));
+        }
+
+        Ok(Self {
+            name: config.name.clone(),
+            folder,
+            mime: config.mime.iter().map(|m| m.to_ascii_lowercase()).collect(),
+            extensions: config
+                .extensions
+                .iter()
+                .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
+            camera: config.camera,
+            gps: config.gps,
+            template: config
+                .template
+                .as_deref()
+                .map(Template::parse)
+                .transpose()?,
+        })
+    }
+
+    /// Whether the file's name or MIME type is claimed by this category and
+    /// its EXIF satisfies the category's camera and GPS conditions
+    pub fn matches(&self, path: &Path, mime_type: Option<&str>, exif: Option<&ExifInfo>) -> bool {
+        let claimed = matches_extension(path, &self.extensions)
+            || mime_type.is_some_and(|mt| self.mime.iter().any(|p| mime_matches(p, mt)));
+        let has_camera = exif.is_some_and(|e| e.camera.is_some());
+        let has_gps = exif.is_some_and(|e| e.has_gps);
+
+        claimed
+            && self.camera.is_none_or(|camera| camera == has_camera)
+            && self.gps.is_none_or(|gps| gps == has_gps)
+    }
+}
+
+/// Whether a file name ends in one of the lowercase `extensions`
+pub(crate) fn matches_extension(path: &Path, extensions: &[String]) -> bool {
+    let Some(file_name) = path.file_name() else {
+        return false;
+    };
+    let file_name = file_name.to_string_lossy().to_ascii_lowercase();
+
+    // Compare against the full name so compound extensions like `tar.gz` work
+    extensions.iter().any(|ext| {
+        file_name.len() > ext.len() + 1
+            && file_name.ends_with(ext.as_str())
+            && file_name[..file_name.len() - ext.len()].ends_with('.')
+    })
+}
+
+/// Match a MIME type against an exact type or a trailing-`*` wildcard such as `image/*`
+pub fn mime_matches(pattern: &str, mime_type: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => mime_type.starts_with(prefix),
+        None => pattern.eq_ignore_ascii_case(mime_type),
+    }
+}
+
+/// Ordered set of categories shared by the scanner, mover and TUI
+#[derive(Debug, Clone)]
+pub struct CategoryRegistry {
+    categories: Vec<Category>,
+    rules: Vec<Rule>,
+    fallback: String,
+    template: Option<Template>,
+    layout: Layout,
+    rename_pattern: RenamePattern,
+}
+
+impl Default for CategoryRegistry {
+    fn default() -> Self {
+        Self {
+            categories: builtin_categories(),
+            rules: Vec::new(),
+            fallback: DEFAULT_FALLBACK.to_string(),
+            template: None,
+            layout: Layout::default(),
+            rename_pattern: RenamePattern::default(),
+        }
+    }
+}
+
+impl CategoryRegistry {
+    /// Build the registry from user config: user categories first, then any
+    /// built-in category the user did not redefine.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut categories = Vec::new();
+        for category in &config.categories {
+            if categories
+                .iter()
+                .any(|c: &Category| c.name == category.name)
+            {
+                return Err(OrganizerError::Config(format!(
+                    "category '{}' is defined more than once",
+                    category.name
+                )));
+            }
+            categories.push(Category::from_config(category)?);
+        }
+
+        let fallback = config
+            .fallback
+            .clone()
+            .unwrap_or_else(|| DEFAULT_FALLBACK.to_string());
+
+        if config.builtin.unwrap_or(true) {
+            for builtin in builtin_categories() {
+                // The built-in catch-all is only useful when it is still the fallback
+                if builtin.name == DEFAULT_FALLBACK && fallback != DEFAULT_FALLBACK {
+                    continue;
+                }
+                if !categories.iter().any(|c| c.name == builtin.name) {
+                    categories.push(builtin);
+                }
+            }
+        }
+
+        if !categories.iter().any(|c| c.name == fallback) {
+            categories.push(Category::new(&fallback, &[], &[]));
+        }
+
+        let mut rules: Vec<Rule> = Vec::new();
+        for (index, rule) in config.rules.iter().enumerate() {
+            let rule = Rule::from_config(rule, index + 1)?;
+            if rules.iter().any(|r| r.name == rule.name) {
+                return Err(OrganizerError::Config(format!(
+                    "rule '{}' is defined more than once",
+                    rule.name
+                )));
+            }
+            // A rule may name a category of its own, which gets a plain folder
+            if !categories.iter().any(|c| c.name == rule.category) {
+                categories.push(Category::from_config(&CategoryConfig {
+                    name: rule.category.clone(),
+                    ..Default::default()
+                })?);
+            }
+            rules.push(rule);
+        }
+
+        let template = config
+            .template
+            .as_deref()
+            .map(Template::parse)
+            .transpose()?;
+
+        Ok(Self {
+            categories,
+            rules,
+            fallback,
+            template,
+            layout: Layout::default(),
+            rename_pattern: RenamePattern::default(),
+        })
+    }
+
+    /// Category name for a file, first match wins
+    pub fn categorize(
+        &self,
+        path: &Path,
+        mime_type: Option<&str>,
+        exif: Option<&ExifInfo>,
+    ) -> &str {
+        self.categories
+            .iter()
+            .find(|c| c.matches(path, mime_type, exif))
+            .map(|c| c.name.as_str())
+            .unwrap_or(&self.fallback)
+    }
+
+    /// Category for a file and the rule that chose it: the first matching
+    /// rule wins, then the first matching category
+    pub fn classify(
+        &self,
+        candidate: &Candidate,
+        exif: Option<&ExifInfo>,
+    ) -> (&str, Option<&Rule>) {
+        match self.rules.iter().find(|r| r.matches(candidate)) {
+            Some(rule) => (&rule.category, Some(rule)),
+            None => (
+                self.categorize(candidate.path, candidate.mime_type, exif),
+                None,
+            ),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn rule(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.name == name)
+    }
+
+    pub fn categories(&self) -> &[Category] {
+        &self.categories
+    }
+
+    /// Category names in display order
+    pub fn names(&self) -> Vec<String> {
+        self.categories.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub fn fallback(&self) -> &str {
+        &self.fallback
+    }
+
+    /// Folder name for a category, defaulting to the category name itself
+    pub fn folder<'a>(&'a self, name: &'a str) -> &'a str {
+        self.categories
+            .iter()
+            .find(|c| c.name == name)
+            .map(|c| c.folder.as_str())
+            .unwrap_or(name)
+    }
+
+    /// Destination template for a category: its own, else the default one
+    pub fn template(&self, name: &str) -> Option<&Template> {
+        self.categories
+            .iter()
+            .find(|c| c.name == name)
+            .and_then(|c| c.template.as_ref())
+            .or(self.template.as_ref())
+    }
+
+    /// Replace the default template used by categories without their own
+    pub fn set_template(&mut self, template: Template) {
+        self.template = Some(template);
+    }
+
+    /// How the subdirectory a file came from carries over to its destination
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+    }
+
+    /// How a file is renamed when its destination is taken
+    pub fn rename_pattern(&self) -> &RenamePattern {
+        &self.rename_pattern
+    }
+
+    pub fn set_rename_pattern(&mut self, pattern: RenamePattern) {
+        self.rename_pattern = pattern;
+    }
+
+    /// Put audio files in a `Music` category laid out by their tags, ahead of
+    /// every other category. A user-defined `Music` category keeps its place and
+    /// matching rules, and only gains the layout if it has no template of its own.
+    pub fn enable_music(&mut self) {
+        let layout = Template::parse(MUSIC_TEMPLATE).expect("built-in template is valid");
+        match self.categories.iter_mut().find(|c| c.name == MUSIC) {
+            Some(music) => {
+                music.template.get_or_insert(layout);
+            }
+            None => {
+                let mut music = Category::new(MUSIC, &["audio/*"], &[]);
+                music.template = Some(layout);
+                self.categories.insert(0, music);
+            }
+        }
+    }
+
+    /// Add a category for whole projects, unless the config already has one
+    pub fn enable_projects(&mut self) {
+        self.ensure_category(PROJECTS_CATEGORY);
+    }
+
+    /// Add a category for directories moved whole that hold no one kind of
+    /// file, unless the config already has one
+    pub fn enable_folders(&mut self) {
+        self.ensure_category(FOLDERS_CATEGORY);
+    }
+
+    fn ensure_category(&mut self, name: &str) {
+        if !self.categories.iter().any(|c| c.name == name) {
+            self.categories.push(Category::new(name, &[], &[]));
+        }
+    }
+
+    /// Whether a directory name is one of the category folders we create
+    pub fn is_category_folder(&self, dir_name: &str) -> bool {
+        self.categories.iter().any(|c| c.folder == dir_name)
+    }
+}
+
+fn builtin_categories() -> Vec<Category> {
+    vec![
+        Category::new(
+            "Multimedia",
+            &[
+                // Images
+                "image/png",
+                "image/jpeg",
+                "image/jpg",
+                "image/gif",
+                "image/webp",
+                "image/svg+xml",
+                "image/bmp",
+                "image/tiff",
+                "image/x-icon",
+                // Audio
+                "audio/mpeg",
+                "audio/ogg",
+                "audio/wav",
+                "audio/webm",
+                "audio/aac",
+                "audio/flac",
+                "audio/x-m4a",
+                // Video
+                "video/mp4",
+                "video/mpeg",
+                "video/ogg",
+                "video/webm",
+                "video/x-msvideo",
+                "video/x-matroska",
+                "video/quicktime",
+            ],
+            &[],
+        ),
+        Category::new(
+            "Docs",
+            &[
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+                "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                "application/vnd.ms-excel",
+                "application/vnd.ms-powerpoint",
+                "application/msword",
+                "application/pdf",
+                "text/html",
+                "text/csv",
+                "text/xml",
+                "application/xml",
+                "text/plain",
+                "text/markdown",
+                "application/rtf",
+            ],
+            &[],
+        ),
+        Category::new(
+            "Compressed",
+            &[
+                "application/zip",
+                "application/x-rar-compressed",
+                "application/x-7z-compressed",
+                "application/gzip",
+                "application/x-tar",
+                "application/x-bzip",
+                "application/x-bzip2",
+                "application/x-xz",
+            ],
+            &[],
+        ),
+        // Code files, stylesheets and JSON end up here along with anything unrecognised
+        Category::new(DEFAULT_FALLBACK, &[], &[]),
+    ]
+}
@@ -1,6 +1,26 @@
+use crate::categories::CategoryRegistry;
+use crate::config::Config;
+use crate::conflict::{prompt_terminal, Asker, Choice, Clash};
+use crate::detect::{detect_mime, DetectMode, DetectionMethod};
+use crate::duplicates::{self, KeepPolicy, DUPLICATES_FOLDER};
 use crate::error::{OrganizerError, Result};
+use crate::filter::{parse_age, parse_size, FilteredCounts, HiddenMode};
+use crate::folders::DirContents;
+use crate::hash::hash_file;
+use crate::journal::{self, ConflictAction, Journal, JournalAction, UndoStatus};
+use crate::metadata::{file_date, read_exif, DateSource, ExifInfo};
+use crate::plan::{apply_action, check_stale, plan_action, Plan, Resolution};
+use crate::projects::ProjectMode;
+use crate::rules::Candidate;
+use crate::scan::{prune_empty_dirs, scan_directory, scan_with_category_folders, FollowSymlinks};
+use crate::tags::{read_tags, AudioTags};
+use crate::template::{Layout, RenamePattern, Template};
+use crate::transfer::TransferMode;
+use crate::trash::Trash;
 use crate::tui::{PreviewApp, ProgressApp, SummaryApp};
-use clap::Parser;
+use crate::watch;
+use chrono::{DateTime, Local, NaiveDateTime};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use colored::Colorize;
 use crossterm::{
     execute,
@@ -9,55 +29,315 @@ use crossterm::{
 use indicatif::{ProgressBar, ProgressStyle};
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::{
-    collections::HashMap,
-    fs,
+    collections::HashSet,
+    fs::{self, File},
     io,
     path::{Path, PathBuf},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 /// File organizer - Automatically organize files into categorized folders
 #[derive(Debug, Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
 pub struct MyOrganizer {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to organize
-    pub path: PathBuf,
+    #[arg(required_unless_present = "explain")]
+    pub path: Option<PathBuf>,
 
     /// Dry run - show what would be done without actually moving files
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub dry_run: bool,
 
     /// Verbose output - show detailed information
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub verbose: bool,
 
-    /// Conflict resolution strategy: skip, overwrite, or rename
-    #[arg(short, long, default_value = "skip", value_parser = ["skip", "overwrite", "rename"])]
+    /// Conflict resolution strategy: skip, overwrite, rename, dedupe (remove the source when it
+    /// is identical to the destination, rename otherwise), newer or larger (keep whichever file
+    /// is newer or larger), or ask (show both files and choose, for one conflict or all)
+    #[arg(
+        short,
+        long,
+        default_value = "skip",
+        value_parser = ["skip", "overwrite", "rename", "dedupe", "newer", "larger", "ask"],
+        global = true
+    )]
     pub conflict: String,
 
+    /// Suffix added before the extension of a file renamed to avoid a conflict, with the
+    /// placeholders n (a counter), timestamp (the file's modification time) and hash (the start
+    /// of its BLAKE3 hash) in braces, e.g. "-{timestamp}" or the default "_" and counter
+    #[arg(long, value_name = "SUFFIX", default_value = "_{n}", global = true)]
+    pub rename_pattern: String,
+
+    /// Delete the files that overwrite and dedupe replace or remove for good, instead of
+    /// moving them to the trash
+    #[arg(long, global = true)]
+    pub no_trash: bool,
+
     /// Recursive - organize files in subdirectories as well
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub recursive: bool,
 
+    /// With --recursive, only descend this many directories below the organized one
+    #[arg(long, value_name = "N", requires = "recursive", global = true)]
+    pub max_depth: Option<usize>,
+
+    /// Hidden files and directories (names starting with a dot): include, exclude, or only
+    /// organize those
+    #[arg(
+        long,
+        value_enum,
+        default_value = "exclude",
+        value_name = "MODE",
+        global = true
+    )]
+    pub hidden: HiddenMode,
+
+    /// Symbolic links to follow: never (move links themselves), files (organize the files
+    /// links point to), or all (also scan linked directories)
+    #[arg(
+        long,
+        value_enum,
+        default_value = "never",
+        value_name = "POLICY",
+        global = true
+    )]
+    pub follow_symlinks: FollowSymlinks,
+
+    /// Project directories (with .git, Cargo.toml, package.json, ...): skip them, move each one
+    /// whole into Projects, or scan them like any other directory
+    #[arg(
+        long,
+        value_enum,
+        default_value = "skip",
+        value_name = "MODE",
+        global = true
+    )]
+    pub projects: ProjectMode,
+
+    /// Organize the subdirectories of the organized directory as whole items, moved intact into
+    /// the category most of their files belong to, a matching rule's, or Folders
+    #[arg(long, conflicts_with = "recursive", global = true)]
+    pub dirs: bool,
+
+    /// After organizing, remove the directories that moving files out of left empty
+    #[arg(long)]
+    pub prune_empty: bool,
+
     /// Interactive mode - show TUI dashboard
     #[arg(short, long)]
     pub interactive: bool,
+
+    /// File type detection: extension, content (magic bytes), or both
+    #[arg(long, value_enum, default_value = "both", global = true)]
+    pub detect: DetectMode,
+
+    /// Watch mode - keep running and organize new files as they arrive
+    #[arg(short, long, conflicts_with_all = ["interactive", "dirs"])]
+    pub watch: bool,
+
+    /// Seconds a new file must stay unchanged before it is moved in watch mode
+    #[arg(long, default_value_t = 2, value_name = "SECS")]
+    pub settle: u64,
+
+    /// Destination path template, e.g. "{category}/{year}/{month}/{name}.{ext}"
+    /// [placeholders: category, year, month, day, name, ext, filename, exif.year, exif.month,
+    /// exif.day, exif.camera, artist, album, title, track; add a width to zero-pad numbers: {track:02}]
+    #[arg(short, long, global = true)]
+    pub template: Option<String>,
+
+    /// Keep the subdirectories files were found in below their category folder
+    #[arg(long, global = true)]
+    pub preserve_structure: bool,
+
+    /// Keep the subdirectories files were found in as part of their name instead, joined with
+    /// this separator (e.g. "__" turns a/notes.txt into a__notes.txt)
+    #[arg(
+        long,
+        value_name = "SEP",
+        value_parser = parse_separator,
+        conflicts_with = "preserve_structure",
+        global = true
+    )]
+    pub flatten_separator: Option<String>,
+
+    /// Put audio files in a Music folder laid out as Artist/Album/NN - Title from their tags
+    #[arg(long, global = true)]
+    pub music: bool,
+
+    /// Date used for the {year}, {month} and {day} template placeholders
+    #[arg(
+        long,
+        value_enum,
+        default_value = "modified",
+        value_name = "SOURCE",
+        global = true
+    )]
+    pub date_from: DateSource,
+
+    /// Only organize files whose path relative to the organized directory matches this glob
+    /// (repeatable)
+    #[arg(long, value_name = "GLOB", global = true)]
+    pub include: Vec<String>,
+
+    /// Leave files and directories matching this glob where they are (repeatable)
+    #[arg(long, value_name = "GLOB", global = true)]
+    pub exclude: Vec<String>,
+
+    /// Only organize files of at least this size, e.g. 100MB
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, global = true)]
+    pub min_size: Option<u64>,
+
+    /// Only organize files of at most this size, e.g. 2GB
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, global = true)]
+    pub max_size: Option<u64>,
+
+    /// Only organize files last modified longer ago than this, e.g. 7d
+    #[arg(long, value_name = "AGE", value_parser = parse_age, global = true)]
+    pub older_than: Option<Duration>,
+
+    /// Only organize files last modified more recently than this, e.g. 12h
+    #[arg(long, value_name = "AGE", value_parser = parse_age, global = true)]
+    pub newer_than: Option<Duration>,
+
+    /// Move files into place, or copy or link them and leave the originals untouched
+    #[arg(long, value_enum, default_value = "move", global = true)]
+    pub mode: TransferMode,
+
+    /// Build the category folders under this directory instead of the organized one
+    #[arg(long, value_name = "DIR", global = true)]
+    pub output: Option<PathBuf>,
+
+    /// Show which rule or category a file matches and why, without moving anything
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["interactive", "watch"])]
+    pub explain: Option<PathBuf>,
+
+    /// Config file with custom categories [default: ~/.config/organizer/config.toml]
+    #[arg(long, value_name = "FILE", global = true)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Undo a previous run by moving its files back (defaults to the latest run)
+    Undo {
+        /// Run ID printed at the end of the run
+        run_id: Option<String>,
+    },
+    /// Find files with identical contents and optionally move the extra copies aside
+    ///
+    /// With --recursive, category folders are searched too, so already organized copies are found.
+    Duplicates {
+        /// Path to search
+        path: PathBuf,
+
+        /// Keep one copy per group and move the rest to a Duplicates folder
+        #[arg(long, value_enum)]
+        keep: Option<KeepPolicy>,
+
+        /// Export the groups to a .json or .csv file
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+    },
+    /// Write every intended move to a JSON plan for review instead of moving anything
+    Plan {
+        /// Path to organize
+        path: PathBuf,
+
+        /// File to write the plan to (default: standard output)
+        #[arg(short, long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Execute a plan written by `organizer plan`, refusing actions whose files changed since
+    Apply {
+        /// Plan file
+        plan: PathBuf,
+    },
+}
+
+impl MyOrganizer {
+    /// Reject combinations of options that clap's own checks can't express
+    pub fn validate(&self) -> std::result::Result<(), clap::Error> {
+        if self.projects == ProjectMode::Move
+            && matches!(self.mode, TransferMode::Copy | TransferMode::Hardlink)
+        {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--projects move places whole directories, which can't be done with --mode {}",
+                    self.mode.as_str()
+                ),
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FileInfo {
     pub path: PathBuf,
     pub mime_type: Option<String>,
+    pub detected_by: DetectionMethod,
     pub category: String,
+    /// Directory the file was found in, relative to the organized directory
+    pub subdir: PathBuf,
+    /// Name of the config rule that chose the category, if one did
+    pub rule: Option<String>,
+    /// For a whole project directory, the marker that identified it
+    pub project: Option<&'static str>,
+    /// For a directory organized as a whole, the files inside it
+    pub contents: Option<DirContents>,
     pub size: u64,
+    /// Date for template placeholders, chosen by `--date-from`
+    pub date: Option<NaiveDateTime>,
+    /// EXIF metadata, for images that have it
+    pub exif: Option<ExifInfo>,
+    /// Artist, album, title and track, for audio files that have tags
+    pub tags: Option<AudioTags>,
+}
+
+/// What `move_file` did with a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveOutcome {
+    /// The file was moved to this destination
+    Moved(PathBuf),
+    /// The file was copied to this destination
+    Copied(PathBuf),
+    /// A symbolic or hard link to the file was created at this destination
+    Linked(PathBuf),
+    Skipped,
+    /// The file was identical to the one at this destination and was removed
+    Duplicate(PathBuf),
 }
 
 #[derive(Debug, Clone)]
 pub struct OrganizeStats {
     pub total_files: usize,
     pub moved: usize,
+    pub copied: usize,
+    pub linked: usize,
     pub skipped: usize,
+    pub deduplicated: usize,
     pub errors: usize,
+    /// Empty directories removed by `--prune-empty`
+    pub pruned: usize,
+}
+
+impl Default for OrganizeStats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OrganizeStats {
@@ -65,27 +345,137 @@ impl OrganizeStats {
         Self {
             total_files: 0,
             moved: 0,
+            copied: 0,
+            linked: 0,
             skipped: 0,
+            deduplicated: 0,
             errors: 0,
+            pruned: 0,
+        }
+    }
+
+    /// Count a file that reached its destination the way `mode` puts files there
+    pub fn add_placed(&mut self, mode: TransferMode) {
+        match mode {
+            TransferMode::Move => self.moved += 1,
+            TransferMode::Copy => self.copied += 1,
+            TransferMode::Symlink | TransferMode::Hardlink => self.linked += 1,
+        }
+    }
+
+    /// Files that reached their destination, whether moved, copied or linked
+    pub fn placed(&self) -> usize {
+        self.moved + self.copied + self.linked
+    }
+}
+
+/// Entry point: dispatch to a subcommand or organize the given path
+pub fn run(args: MyOrganizer) -> Result<()> {
+    match &args.command {
+        Some(Command::Undo { run_id }) => undo_run(run_id.as_deref(), &args),
+        Some(Command::Duplicates { path, keep, export }) => {
+            duplicates_run(path, *keep, export.as_deref(), &args)
         }
+        Some(Command::Plan { path, out }) => plan_run(path, out.as_deref(), &args),
+        Some(Command::Apply { plan }) => apply_run(plan, &args),
+        None => match &args.explain {
+            Some(file) => explain_run(file, &args),
+            None => organizer_files(args),
+        },
     }
 }
 
-pub fn organizer_files(args: MyOrganizer) -> Result<()> {
-    let path = &args.path;
+/// Categories from the config file, with `--template` as the default template
+fn load_registry(args: &MyOrganizer) -> Result<CategoryRegistry> {
+    let config = Config::load(args.config.as_deref())?;
+    let mut registry = CategoryRegistry::from_config(&config)?;
+    if let Some(template) = &args.template {
+        registry.set_template(Template::parse(template)?);
+    }
+    if args.music {
+        registry.enable_music();
+    }
+    if args.projects == ProjectMode::Move {
+        registry.enable_projects();
+    }
+    if args.dirs {
+        registry.enable_folders();
+    }
+    registry.set_rename_pattern(RenamePattern::parse(&args.rename_pattern)?);
+    if args.preserve_structure {
+        registry.set_layout(Layout::Preserve);
+    } else if let Some(separator) = &args.flatten_separator {
+        registry.set_layout(Layout::Flatten(separator.clone()));
+    }
+    Ok(registry)
+}
 
-    // Validate path
+fn validate_dir(path: &Path) -> Result<()> {
     if !path.exists() {
-        return Err(OrganizerError::PathNotFound(path.clone()));
+        return Err(OrganizerError::PathNotFound(path.to_path_buf()));
     }
     if !path.is_dir() {
-        return Err(OrganizerError::PathNotDirectory(path.clone()));
+        return Err(OrganizerError::PathNotDirectory(path.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// Directory the category folders go under: `--output` if given, else the
+/// organized directory itself. A recursive scan must not reach the output,
+/// or files already organized would be picked up again.
+fn output_root(path: &Path, args: &MyOrganizer) -> Result<PathBuf> {
+    let Some(output) = &args.output else {
+        return Ok(path.to_path_buf());
+    };
+    if output.exists() && !output.is_dir() {
+        return Err(OrganizerError::PathNotDirectory(output.clone()));
     }
 
+    let scanned = fs::canonicalize(path)?;
+    let resolved = resolve(output);
+    if args.recursive && resolved != scanned && resolved.starts_with(&scanned) {
+        return Err(OrganizerError::InvalidPath(format!(
+            "output directory {} is inside {}, which is scanned recursively",
+            output.display(),
+            path.display()
+        )));
+    }
+    Ok(output.clone())
+}
+
+/// `path` with symlinks resolved as far as it exists, so it can be compared
+/// with canonical paths before it has been created
+fn resolve(path: &Path) -> PathBuf {
+    let path = journal::absolute(path);
+    for ancestor in path.ancestors() {
+        if let Ok(canonical) = fs::canonicalize(ancestor) {
+            return canonical.join(path.strip_prefix(ancestor).unwrap_or(Path::new("")));
+        }
+    }
+    path
+}
+
+pub fn organizer_files(args: MyOrganizer) -> Result<()> {
+    let path = args
+        .path
+        .as_deref()
+        .ok_or_else(|| OrganizerError::InvalidPath("No path given".to_string()))?;
+
+    // Validate path
+    validate_dir(path)?;
+    let output = output_root(path, &args)?;
+
+    let registry = load_registry(&args)?;
+
     println!(
-        "{} {} {}",
+        "{} {}{} {}",
         "Organizing".bright_cyan().bold(),
         path.display().to_string().bright_yellow(),
+        if output != path {
+            format!(" into {}", output.display().to_string().bright_yellow())
+        } else {
+            String::new()
+        },
         if args.dry_run {
             "(DRY RUN)".bright_magenta().bold()
         } else {
@@ -98,21 +488,51 @@ pub fn organizer_files(args: MyOrganizer) -> Result<()> {
         println!("  Dry run: {}", args.dry_run);
         println!("  Verbose: {}", args.verbose);
         println!("  Conflict strategy: {}", args.conflict);
-        println!("  Recursive: {}", args.recursive);
+        println!("  Mode: {}", args.mode.as_str());
+        match args.max_depth {
+            Some(depth) if args.recursive => println!("  Recursive: true (max depth {})", depth),
+            _ => println!("  Recursive: {}", args.recursive),
+        }
+        println!("  Hidden files: {:?}", args.hidden);
+        println!("  Follow symlinks: {:?}", args.follow_symlinks);
+        println!("  Detection: {:?}", args.detect);
+        if let Some(template) = &args.template {
+            println!("  Template: {} (dates from {:?})", template, args.date_from);
+        }
+        println!("  Watch: {}", args.watch);
+        println!("  Categories: {}", registry.names().join(", "));
         println!();
     }
 
     // Scan directory and categorize files
-    let files = scan_directory(path, args.recursive, args.verbose)?;
+    let scan = scan_directory(path, &registry, &args)?;
+    let files = scan.files;
+
+    let mut journal = if args.dry_run {
+        Journal::disabled()
+    } else {
+        Journal::open(&journal::default_journal_path()?)?
+    };
 
     if files.is_empty() {
         println!("{}", "No files to organize".bright_yellow());
+        if args.watch {
+            return watch::watch(path, &output, &registry, &mut journal, &args);
+        }
         return Ok(());
     }
 
     // Interactive mode with TUI
     if args.interactive {
-        return run_interactive_mode(files, path, &args);
+        return run_interactive_mode(
+            files,
+            scan.filtered,
+            path,
+            &output,
+            &registry,
+            &mut journal,
+            &args,
+        );
     }
 
     // Create progress bar
@@ -133,72 +553,128 @@ pub fn organizer_files(args: MyOrganizer) -> Result<()> {
     if args.dry_run {
         println!();
         println!(
-            "{:<50} {:<12} {:<15} {}",
+            "{:<50} {:<12} {:<9} {:<25} {}",
             "File".bright_cyan().bold(),
             "Size".bright_cyan().bold(),
-            "Category".bright_cyan().bold(),
+            "Action".bright_cyan().bold(),
+            "Destination".bright_cyan().bold(),
             "MIME Type".bright_cyan().bold()
         );
-        println!("{}", "─".repeat(100).bright_black());
+        println!("{}", "─".repeat(120).bright_black());
     }
 
     // Move files
     let mut stats = OrganizeStats::new();
     stats.total_files = files.len();
+    let mut vacated = Vec::new();
+    let mut asker = Asker::default();
 
     for file_info in files {
+        let name = file_info
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
         if !args.dry_run {
+            pb.set_message(format!("Processing: {}", name));
+        }
+
+        // Only copies report progress
+        let mut report_copy = |copied: u64, total: u64| {
             pb.set_message(format!(
-                "Processing: {}",
-                file_info
-                    .path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
+                "Copying: {} ({}%)",
+                name,
+                copied * 100 / total.max(1)
             ));
-        }
+        };
 
-        match move_file(&file_info, path, &args) {
-            Ok(moved) => {
-                if moved {
-                    stats.moved += 1;
+        let outcome =
+            conflict_strategy(&file_info, &output, &registry, &args, &mut asker, |clash| {
+                pb.suspend(|| prompt_terminal(clash))
+            })
+            .and_then(|conflict| {
+                move_file(
+                    &file_info,
+                    &output,
+                    &registry,
+                    &mut journal,
+                    &args,
+                    conflict,
+                    &mut report_copy,
+                )
+            });
+        if matches!(
+            outcome,
+            Ok(MoveOutcome::Moved(_) | MoveOutcome::Duplicate(_))
+        ) {
+            vacated.push(file_info.path.clone());
+        }
+        match outcome {
+            Ok(
+                MoveOutcome::Moved(destination)
+                | MoveOutcome::Copied(destination)
+                | MoveOutcome::Linked(destination),
+            ) => {
+                stats.add_placed(args.mode);
+                if args.dry_run {
+                    let size_str = format_size(file_info.size);
+                    let mime_str =
+                        match (&file_info.mime_type, file_info.project, &file_info.contents) {
+                            (_, Some(marker), _) => format!("project ({})", marker),
+                            (_, None, Some(contents)) => {
+                                format!("folder ({} files)", contents.files)
+                            }
+                            (Some(mime), _, _) => format!("{} ({})", mime, file_info.detected_by),
+                            (None, _, _) => "unknown".to_string(),
+                        };
+
+                    let folder = relative(destination.parent().unwrap_or(&output), &output);
+
+                    println!(
+                        "{:<50} {:<12} {:<9} {:<25} {}",
+                        truncate_str(&name, 48),
+                        size_str.bright_yellow(),
+                        args.mode.as_str(),
+                        truncate_str(&folder, 24).bright_cyan(),
+                        truncate_str(&mime_str, 40).bright_black()
+                    );
+                } else if args.verbose {
+                    pb.println(format!(
+                        "  {} {} -> {}",
+                        "✓".bright_green(),
+                        file_info.path.display(),
+                        relative(&destination, &output).bright_cyan()
+                    ));
+                }
+            }
+            Ok(MoveOutcome::Skipped) => {
+                stats.skipped += 1;
+                if args.verbose {
+                    pb.println(format!(
+                        "  {} {} (already exists)",
+                        "⊘".bright_yellow(),
+                        name
+                    ));
+                }
+            }
+            Ok(MoveOutcome::Duplicate(existing)) => {
+                stats.deduplicated += 1;
+                let msg = format!(
+                    "  {} {} (duplicate of {}{})",
+                    "≡".bright_blue(),
+                    file_info.path.display(),
+                    existing.display(),
                     if args.dry_run {
-                        let filename = file_info
-                            .path
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy();
-                        let size_str = format_size(file_info.size);
-                        let mime_str = file_info
-                            .mime_type
-                            .as_deref()
-                            .unwrap_or("unknown")
-                            .to_string();
-
-                        println!(
-                            "{:<50} {:<12} {:<15} {}",
-                            truncate_str(&filename, 48),
-                            size_str.bright_yellow(),
-                            file_info.category.bright_cyan(),
-                            truncate_str(&mime_str, 40).bright_black()
-                        );
-                    } else if args.verbose {
-                        pb.println(format!(
-                            "  {} {} -> {}",
-                            "✓".bright_green(),
-                            file_info.path.display(),
-                            file_info.category.bright_cyan()
-                        ));
-                    }
-                } else {
-                    stats.skipped += 1;
-                    if args.verbose {
-                        pb.println(format!(
-                            "  {} {} (already exists)",
-                            "⊘".bright_yellow(),
-                            file_info.path.file_name().unwrap_or_default().to_string_lossy()
-                        ));
+                        ", would be removed"
+                    } else {
+                        ", removed"
                     }
+                );
+                if args.dry_run {
+                    println!("{}", msg);
+                } else if args.verbose {
+                    pb.println(msg);
                 }
             }
             Err(e) => {
@@ -226,15 +702,233 @@ pub fn organizer_files(args: MyOrganizer) -> Result<()> {
         pb.finish_with_message("Done!");
     }
 
+    if args.prune_empty {
+        let pruned = prune_empty_dirs(path, &vacated, &registry, &mut journal, args.dry_run)?;
+        if args.dry_run || args.verbose {
+            for dir in &pruned {
+                println!(
+                    "  {} {}{}",
+                    "✂".bright_blue(),
+                    relative(dir, path),
+                    if args.dry_run {
+                        " (empty, would be removed)"
+                    } else {
+                        " (empty, removed)"
+                    }
+                );
+            }
+        }
+        stats.pruned = pruned.len();
+    }
+
     // Print summary
     println!();
     println!("{}", "Summary:".bright_cyan().bold());
     println!("  Total files: {}", stats.total_files);
     println!(
         "  {} {}",
-        "Moved:".bright_green(),
-        stats.moved.to_string().bright_green().bold()
+        format!("{}:", args.mode.done_label()).bright_green(),
+        stats.placed().to_string().bright_green().bold()
+    );
+    if stats.skipped > 0 {
+        println!(
+            "  {} {}",
+            "Skipped:".bright_yellow(),
+            stats.skipped.to_string().bright_yellow().bold()
+        );
+    }
+    if stats.deduplicated > 0 {
+        println!(
+            "  {} {}",
+            "Duplicates removed:".bright_blue(),
+            stats.deduplicated.to_string().bright_blue().bold()
+        );
+    }
+    if stats.pruned > 0 {
+        println!(
+            "  {} {}",
+            "Empty directories removed:".bright_blue(),
+            stats.pruned.to_string().bright_blue().bold()
+        );
+    }
+    if stats.errors > 0 {
+        println!(
+            "  {} {}",
+            "Errors:".bright_red(),
+            stats.errors.to_string().bright_red().bold()
+        );
+    }
+    print_undo_hint(&journal, &stats, &args);
+
+    if args.watch {
+        println!();
+        return watch::watch(path, &output, &registry, &mut journal, &args);
+    }
+
+    Ok(())
+}
+
+fn print_undo_hint(journal: &Journal, stats: &OrganizeStats, args: &MyOrganizer) {
+    if !args.dry_run && stats.placed() + stats.deduplicated + stats.pruned > 0 {
+        println!();
+        println!(
+            "  {} {}  (undo with: organizer undo {})",
+            "Run ID:".bright_white(),
+            journal.run_id().bright_yellow(),
+            journal.run_id()
+        );
+    }
+}
+
+fn plan_run(path: &Path, out: Option<&Path>, args: &MyOrganizer) -> Result<()> {
+    validate_dir(path)?;
+    let output = output_root(path, args)?;
+    if args.conflict == "ask" {
+        return Err(OrganizerError::Plan(
+            "--conflict ask can't be decided ahead of time, choose another strategy".to_string(),
+        ));
+    }
+    let registry = load_registry(args)?;
+    let files = scan_directory(path, &registry, args)?.files;
+    let plan = Plan::build(&files, path, &output, &registry, &args.conflict, args.mode)?;
+
+    let Some(out) = out else {
+        return plan.write(io::stdout().lock());
+    };
+    plan.write(File::create(out)?)?;
+
+    println!(
+        "{} {} {}",
+        "Planned".bright_cyan().bold(),
+        plan.actions.len().to_string().bright_green().bold(),
+        format!("actions for {}", path.display())
+            .bright_cyan()
+            .bold()
+    );
+    println!("  Moves: {}", plan.count(Resolution::Move));
+    println!("  Renames: {}", plan.count(Resolution::Rename));
+    println!("  Overwrites: {}", plan.count(Resolution::Overwrite));
+    println!(
+        "  Duplicates to remove: {}",
+        plan.count(Resolution::RemoveDuplicate)
+    );
+    println!("  Skipped: {}", plan.count(Resolution::Skip));
+    println!();
+    println!(
+        "  {} {}  (apply with: organizer apply {})",
+        "Plan:".bright_white(),
+        out.display().to_string().bright_yellow(),
+        out.display()
+    );
+    Ok(())
+}
+
+fn apply_run(plan_path: &Path, args: &MyOrganizer) -> Result<()> {
+    let plan = Plan::load(plan_path)?;
+
+    println!(
+        "{} {} {}",
+        "Applying".bright_cyan().bold(),
+        plan_path.display().to_string().bright_yellow(),
+        if args.dry_run {
+            "(DRY RUN)".bright_magenta().bold()
+        } else {
+            "".clear()
+        }
+    );
+
+    let mut journal = if args.dry_run {
+        Journal::disabled()
+    } else {
+        Journal::open(&journal::default_journal_path()?)?
+    };
+
+    let mut stats = OrganizeStats::new();
+    stats.total_files = plan.actions.len();
+    let mut refused = 0;
+
+    let output = plan.output.as_deref().unwrap_or(&plan.base_path);
+    let trash = trash(args)?;
+    for action in &plan.actions {
+        let name = relative(&action.source, &plan.base_path);
+        if let Some(reason) = check_stale(action) {
+            refused += 1;
+            println!("  {} {} (refused: {})", "⊘".bright_yellow(), name, reason);
+            continue;
+        }
+
+        match apply_action(
+            action,
+            plan.mode,
+            &mut journal,
+            trash.as_ref(),
+            args.dry_run,
+            &mut |_, _| {},
+        ) {
+            Ok(
+                MoveOutcome::Moved(destination)
+                | MoveOutcome::Copied(destination)
+                | MoveOutcome::Linked(destination),
+            ) => {
+                stats.add_placed(plan.mode);
+                if args.verbose {
+                    println!(
+                        "  {} {} -> {}",
+                        "✓".bright_green(),
+                        name,
+                        relative(&destination, output).bright_cyan()
+                    );
+                }
+            }
+            Ok(MoveOutcome::Skipped) => stats.skipped += 1,
+            Ok(MoveOutcome::Duplicate(existing)) => {
+                stats.deduplicated += 1;
+                if args.verbose {
+                    println!(
+                        "  {} {} (duplicate of {}{})",
+                        "≡".bright_blue(),
+                        name,
+                        relative(&existing, output),
+                        if args.dry_run {
+                            ", would be removed"
+                        } else {
+                            ", removed"
+                        }
+                    );
+                }
+            }
+            Err(e) => {
+                stats.errors += 1;
+                println!(
+                    "  {} {} - {}",
+                    "✗".bright_red(),
+                    name,
+                    e.to_string().bright_red()
+                );
+            }
+        }
+    }
+
+    println!();
+    println!("{}", "Summary:".bright_cyan().bold());
+    println!("  Total actions: {}", stats.total_files);
+    let done = if args.dry_run {
+        format!("Would {}:", plan.mode.as_str())
+    } else {
+        format!("{}:", plan.mode.done_label())
+    };
+    println!(
+        "  {} {}",
+        done.bright_green(),
+        stats.placed().to_string().bright_green().bold()
     );
+    if stats.deduplicated > 0 {
+        println!(
+            "  {} {}",
+            "Duplicates removed:".bright_blue(),
+            stats.deduplicated.to_string().bright_blue().bold()
+        );
+    }
     if stats.skipped > 0 {
         println!(
             "  {} {}",
@@ -242,6 +936,13 @@ pub fn organizer_files(args: MyOrganizer) -> Result<()> {
             stats.skipped.to_string().bright_yellow().bold()
         );
     }
+    if refused > 0 {
+        println!(
+            "  {} {}",
+            "Refused (changed since planning):".bright_yellow(),
+            refused.to_string().bright_yellow().bold()
+        );
+    }
     if stats.errors > 0 {
         println!(
             "  {} {}",
@@ -249,15 +950,417 @@ pub fn organizer_files(args: MyOrganizer) -> Result<()> {
             stats.errors.to_string().bright_red().bold()
         );
     }
+    print_undo_hint(&journal, &stats, args);
 
     Ok(())
 }
 
-fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganizer) -> Result<()> {
+fn duplicates_run(
+    path: &Path,
+    keep: Option<KeepPolicy>,
+    export: Option<&Path>,
+    args: &MyOrganizer,
+) -> Result<()> {
+    validate_dir(path)?;
+    let registry = load_registry(args)?;
+
+    println!(
+        "{} {} {}",
+        "Finding duplicates in".bright_cyan().bold(),
+        path.display().to_string().bright_yellow(),
+        if args.dry_run {
+            "(DRY RUN)".bright_magenta().bold()
+        } else {
+            "".clear()
+        }
+    );
+
+    let files = scan_with_category_folders(path, &registry, args)?.files;
+    let groups = duplicates::find_duplicates(&files);
+
+    if groups.is_empty() {
+        println!("{}", "No duplicates found".bright_green());
+        return Ok(());
+    }
+
+    for (index, group) in groups.iter().enumerate() {
+        println!();
+        println!(
+            "{} {}  {} x {}  {}",
+            "Group".bright_cyan().bold(),
+            (index + 1).to_string().bright_cyan().bold(),
+            group.files.len(),
+            format_size(group.size).bright_yellow(),
+            group.hash[..16].bright_black()
+        );
+        let keeper = keep.map(|policy| group.keeper(policy));
+        for dup in &group.files {
+            let marker = match keeper {
+                Some(k) if k == dup.path => "keep".bright_green(),
+                Some(_) => "move".bright_yellow(),
+                None => "    ".clear(),
+            };
+            println!("  {} {}", marker, dup.path.display());
+        }
+    }
+
+    let copies: usize = groups.iter().map(|g| g.files.len() - 1).sum();
+    let wasted: u64 = groups.iter().map(|g| g.wasted()).sum();
+    println!();
+    println!("{}", "Summary:".bright_cyan().bold());
+    println!("  Groups: {}", groups.len());
+    println!(
+        "  Extra copies: {}",
+        copies.to_string().bright_yellow().bold()
+    );
+    println!(
+        "  Wasted space: {}",
+        format_size(wasted).bright_yellow().bold()
+    );
+
+    if let Some(export_path) = export {
+        duplicates::export(&groups, keep, export_path)?;
+        println!("  Exported to: {}", export_path.display());
+    }
+
+    let Some(policy) = keep else {
+        return Ok(());
+    };
+
+    let mut journal = if args.dry_run {
+        Journal::disabled()
+    } else {
+        Journal::open(&journal::default_journal_path()?)?
+    };
+
+    let mut stats = OrganizeStats::new();
+    stats.total_files = copies;
+    for (source, result) in
+        duplicates::move_extras(&groups, policy, path, &mut journal, args.dry_run)
+    {
+        match result {
+            Ok(destination) => {
+                stats.moved += 1;
+                if args.verbose {
+                    println!(
+                        "  {} {} -> {}",
+                        "✓".bright_green(),
+                        source.display(),
+                        destination.display()
+                    );
+                }
+            }
+            Err(e) => {
+                stats.errors += 1;
+                println!(
+                    "  {} {} - {}",
+                    "✗".bright_red(),
+                    source.display(),
+                    e.to_string().bright_red()
+                );
+            }
+        }
+    }
+
+    println!(
+        "  {} {} {}",
+        if args.dry_run {
+            "Would move:"
+        } else {
+            "Moved:"
+        }
+        .bright_green(),
+        stats.moved.to_string().bright_green().bold(),
+        format!("(to {})", path.join(DUPLICATES_FOLDER).display()).bright_black()
+    );
+    if stats.errors > 0 {
+        println!(
+            "  {} {}",
+            "Errors:".bright_red(),
+            stats.errors.to_string().bright_red().bold()
+        );
+    }
+    print_undo_hint(&journal, &stats, args);
+
+    Ok(())
+}
+
+/// Print every rule tried on a file up to the one that matched, the
+/// category it ends up in and where it would go
+fn explain_run(path: &Path, args: &MyOrganizer) -> Result<()> {
+    if !path.exists() {
+        return Err(OrganizerError::PathNotFound(path.to_path_buf()));
+    }
+    if !path.is_file() {
+        return Err(OrganizerError::InvalidPath(format!(
+            "{} is not a file",
+            path.display()
+        )));
+    }
+    let registry = load_registry(args)?;
+    let metadata = fs::metadata(path)?;
+    let info = file_info(path.to_path_buf(), &metadata, &registry, args);
+    let candidate = Candidate::new(path, info.mime_type.as_deref(), &metadata);
+
+    println!(
+        "{} {}",
+        "Explaining".bright_cyan().bold(),
+        path.display().to_string().bright_yellow()
+    );
+    match &info.mime_type {
+        Some(mime) => println!("  Type: {} (detected by {})", mime, info.detected_by),
+        None => println!("  Type: unknown"),
+    }
+    println!("  Size: {}", format_size(info.size));
+    println!();
+
+    if registry.rules().is_empty() {
+        println!("{}", "No rules configured".bright_black());
+    }
+    for rule in registry.rules() {
+        let checks = rule.explain(&candidate);
+        let matched = checks.iter().all(|c| c.passed);
+        println!(
+            "{} \"{}\" -> {}: {}",
+            "Rule".bright_white(),
+            rule.name,
+            rule.category,
+            if matched {
+                "matched".bright_green().bold()
+            } else {
+                "no match".bright_red()
+            }
+        );
+        for check in &checks {
+            println!(
+                "  {} {} ({})",
+                if check.passed {
+                    "✓".bright_green()
+                } else {
+                    "✗".bright_red()
+                },
+                check.condition,
+                check.actual.bright_black()
+            );
+        }
+        if matched {
+            break;
+        }
+    }
+    println!();
+
+    let reason = match &info.rule {
+        Some(rule) => format!("rule \"{}\"", rule),
+        None if registry.categories().iter().any(|c| {
+            c.name == info.category
+                && c.matches(path, info.mime_type.as_deref(), info.exif.as_ref())
+        }) =>
+        {
+            "its extension or type".to_string()
+        }
+        None => "nothing else matched".to_string(),
+    };
+    println!(
+        "  {} {} (from {})",
+        "Category:".bright_white(),
+        info.category.bright_green().bold(),
+        reason
+    );
+    let destination = destination_path(&info, &registry)?;
+    let destination = match args.output.as_deref().or(args.path.as_deref()) {
+        Some(root) => root.join(destination),
+        None => destination,
+    };
+    println!(
+        "  {} {}",
+        "Destination:".bright_white(),
+        destination.display().to_string().bright_yellow()
+    );
+    Ok(())
+}
+
+fn undo_run(run_id: Option<&str>, args: &MyOrganizer) -> Result<()> {
+    let journal_path = journal::default_journal_path()?;
+    let report = journal::undo(&journal_path, run_id, args.dry_run)?;
+
+    println!(
+        "{} {} {}",
+        "Undoing run".bright_cyan().bold(),
+        report.run_id.bright_yellow(),
+        if args.dry_run {
+            "(DRY RUN)".bright_magenta().bold()
+        } else {
+            "".clear()
+        }
+    );
+
+    for (action, status) in &report.results {
+        match (action, status) {
+            (
+                JournalAction::Move {
+                    source,
+                    destination,
+                    conflict,
+                    ..
+                },
+                UndoStatus::Restored,
+            ) => {
+                if args.verbose || args.dry_run {
+                    println!(
+                        "  {} {} -> {}",
+                        "✓".bright_green(),
+                        destination.display(),
+                        source.display()
+                    );
+                }
+                let trashed = report
+                    .results
+                    .iter()
+                    .any(|(action, _)| matches!(action, JournalAction::Trash { path, .. } if path == destination));
+                if *conflict == ConflictAction::Overwrite && !trashed {
+                    println!(
+                        "  {} {} had replaced an existing file, which cannot be restored",
+                        "!".bright_yellow(),
+                        destination.display()
+                    );
+                }
+            }
+            (
+                JournalAction::CreateFile {
+                    destination, mode, ..
+                },
+                UndoStatus::Restored,
+            ) => {
+                if args.verbose || args.dry_run {
+                    println!(
+                        "  {} removed {} ({})",
+                        "✓".bright_green(),
+                        destination.display(),
+                        mode.as_str()
+                    );
+                }
+            }
+            (
+                JournalAction::Move { destination, .. }
+                | JournalAction::CreateFile { destination, .. },
+                UndoStatus::Skipped(reason),
+            ) => {
+                println!(
+                    "  {} {} ({})",
+                    "⊘".bright_yellow(),
+                    destination.display(),
+                    reason.bright_yellow()
+                );
+            }
+            (
+                JournalAction::RemoveDuplicate {
+                    path,
+                    duplicate_of,
+                    trashed,
+                },
+                UndoStatus::Restored,
+            ) => {
+                if args.verbose || args.dry_run {
+                    let from = match trashed {
+                        Some(_) => "trash".to_string(),
+                        None => duplicate_of.display().to_string(),
+                    };
+                    println!(
+                        "  {} {} -> {} (removed duplicate)",
+                        "✓".bright_green(),
+                        from,
+                        path.display()
+                    );
+                }
+            }
+            (JournalAction::Trash { path, .. }, UndoStatus::Restored) => {
+                if args.verbose || args.dry_run {
+                    println!(
+                        "  {} trash -> {} (replaced file)",
+                        "✓".bright_green(),
+                        path.display()
+                    );
+                }
+            }
+            (JournalAction::Trash { path, .. }, UndoStatus::Skipped(reason)) => {
+                println!(
+                    "  {} could not restore replaced file {} ({})",
+                    "⊘".bright_yellow(),
+                    path.display(),
+                    reason.bright_yellow()
+                );
+            }
+            (JournalAction::RemoveDuplicate { path, .. }, UndoStatus::Skipped(reason)) => {
+                println!(
+                    "  {} {} ({})",
+                    "⊘".bright_yellow(),
+                    path.display(),
+                    reason.bright_yellow()
+                );
+            }
+            (JournalAction::CreateDir { path }, UndoStatus::Restored) => {
+                if args.verbose {
+                    println!("  {} removed {}", "✓".bright_green(), path.display());
+                }
+            }
+            (JournalAction::CreateDir { path }, UndoStatus::Skipped(reason)) => {
+                if args.verbose {
+                    println!(
+                        "  {} kept {} ({})",
+                        "⊘".bright_yellow(),
+                        path.display(),
+                        reason
+                    );
+                }
+            }
+            (JournalAction::RemoveDir { path }, UndoStatus::Restored) => {
+                if args.verbose {
+                    println!("  {} recreated {}", "✓".bright_green(), path.display());
+                }
+            }
+            (JournalAction::RemoveDir { path }, UndoStatus::Skipped(reason)) => {
+                println!(
+                    "  {} could not recreate {} ({})",
+                    "⊘".bright_yellow(),
+                    path.display(),
+                    reason.bright_yellow()
+                );
+            }
+        }
+    }
+
+    println!();
+    println!("{}", "Summary:".bright_cyan().bold());
+    println!(
+        "  {} {}",
+        "Restored:".bright_green(),
+        report.restored().to_string().bright_green().bold()
+    );
+    if report.skipped() > 0 {
+        println!(
+            "  {} {}",
+            "Skipped:".bright_yellow(),
+            report.skipped().to_string().bright_yellow().bold()
+        );
+    }
+
+    Ok(())
+}
+
+fn run_interactive_mode(
+    files: Vec<FileInfo>,
+    filtered: FilteredCounts,
+    scanned: &Path,
+    base_path: &Path,
+    registry: &CategoryRegistry,
+    journal: &mut Journal,
+    args: &MyOrganizer,
+) -> Result<()> {
     if args.dry_run {
         // Show preview dashboard
-        let mut app = PreviewApp::new(files);
-        app.run().map_err(|e| OrganizerError::IoError(e))?;
+        let mut app = PreviewApp::new(files, registry.names());
+        app.filtered = filtered;
+        app.run().map_err(OrganizerError::IoError)?;
 
         if app.should_quit {
             println!("{}", "Operation cancelled".bright_yellow());
@@ -268,15 +1371,18 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
     }
 
     // Setup TUI for progress
-    enable_raw_mode().map_err(|e| OrganizerError::IoError(e))?;
+    enable_raw_mode().map_err(OrganizerError::IoError)?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen).map_err(|e| OrganizerError::IoError(e))?;
+    execute!(stdout, EnterAlternateScreen).map_err(OrganizerError::IoError)?;
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).map_err(|e| OrganizerError::IoError(e))?;
+    let mut terminal = Terminal::new(backend).map_err(OrganizerError::IoError)?;
 
-    let mut progress_app = ProgressApp::new(files.len());
+    let mut progress_app = ProgressApp::new(files.len(), registry.names());
     progress_app.stats.total_files = files.len();
+    progress_app.mode = args.mode;
     let mut total_size_moved = 0u64;
+    let mut vacated = Vec::new();
+    let mut asker = Asker::default();
 
     // Start timer
     let start_time = Instant::now();
@@ -287,16 +1393,50 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
     for (index, file_info) in files.iter().enumerate() {
         progress_app.update_current(file_info);
 
-        // Process the file
-        match move_file(file_info, base_path, args) {
-            Ok(moved) => {
-                if moved {
-                    progress_app.stats.moved += 1;
-                    progress_app.update_category(&file_info.category, file_info.size);
-                    total_size_moved += file_info.size;
-                } else {
-                    progress_app.stats.skipped += 1;
-                }
+        // A conflict under --conflict ask waits for an answer in a dialog
+        let conflict =
+            conflict_strategy(file_info, base_path, registry, args, &mut asker, |clash| {
+                progress_app.ask(&mut terminal, clash)
+            });
+
+        // Process the file, redrawing while a large file is copied
+        let mut report_copy = |copied: u64, total: u64| {
+            progress_app.update_copy(copied, total);
+            if last_render.elapsed() >= render_interval {
+                let _ = terminal.draw(|f| progress_app.render(f));
+                last_render = Instant::now();
+            }
+        };
+        let outcome = conflict.and_then(|conflict| {
+            move_file(
+                file_info,
+                base_path,
+                registry,
+                journal,
+                args,
+                conflict,
+                &mut report_copy,
+            )
+        });
+        progress_app.copy_progress = None;
+        if matches!(
+            outcome,
+            Ok(MoveOutcome::Moved(_) | MoveOutcome::Duplicate(_))
+        ) {
+            vacated.push(file_info.path.clone());
+        }
+
+        match outcome {
+            Ok(MoveOutcome::Moved(_) | MoveOutcome::Copied(_) | MoveOutcome::Linked(_)) => {
+                progress_app.stats.add_placed(args.mode);
+                progress_app.update_category(&file_info.category, file_info.size);
+                total_size_moved += file_info.size;
+            }
+            Ok(MoveOutcome::Skipped) => {
+                progress_app.stats.skipped += 1;
+            }
+            Ok(MoveOutcome::Duplicate(_)) => {
+                progress_app.stats.deduplicated += 1;
             }
             Err(_) => {
                 progress_app.stats.errors += 1;
@@ -308,24 +1448,31 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
         if should_render {
             terminal
                 .draw(|f| progress_app.render(f))
-                .map_err(|e| OrganizerError::IoError(e))?;
+                .map_err(OrganizerError::IoError)?;
             last_render = Instant::now();
         }
     }
 
+    if args.prune_empty {
+        // Still in the alternate screen, so a failure is counted rather than returned
+        match prune_empty_dirs(scanned, &vacated, registry, journal, false) {
+            Ok(pruned) => progress_app.stats.pruned = pruned.len(),
+            Err(_) => progress_app.stats.errors += 1,
+        }
+    }
+
     // Final render to ensure 100% is visible
     terminal
         .draw(|f| progress_app.render(f))
-        .map_err(|e| OrganizerError::IoError(e))?;
+        .map_err(OrganizerError::IoError)?;
 
     // Show completion for a moment
     std::thread::sleep(std::time::Duration::from_millis(500));
 
     // Restore terminal before showing summary
-    disable_raw_mode().map_err(|e| OrganizerError::IoError(e))?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)
-        .map_err(|e| OrganizerError::IoError(e))?;
-    terminal.show_cursor().map_err(|e| OrganizerError::IoError(e))?;
+    disable_raw_mode().map_err(OrganizerError::IoError)?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(OrganizerError::IoError)?;
+    terminal.show_cursor().map_err(OrganizerError::IoError)?;
 
     // Calculate elapsed time
     let elapsed_time = start_time.elapsed();
@@ -338,11 +1485,13 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
     let summary_app = SummaryApp::new(
         progress_app.stats,
         progress_app.category_progress,
+        progress_app.categories,
         elapsed_time,
         total_size_moved,
+        args.mode,
     );
 
-    summary_app.run().map_err(|e| OrganizerError::IoError(e))?;
+    summary_app.run().map_err(OrganizerError::IoError)?;
 
     // Print text summary to terminal for permanent record
     println!();
@@ -352,13 +1501,39 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
     println!();
 
     println!("{}", "Summary:".bright_cyan().bold());
-    println!("  {} {}", "Total files:".bright_white(), stats_clone.total_files.to_string().bright_yellow());
-    println!("  {} {}", "✓ Moved:".bright_green(), stats_clone.moved.to_string().bright_green().bold());
-    println!("  {} {}", "⊘ Skipped:".bright_yellow(), stats_clone.skipped.to_string().bright_yellow());
+    println!(
+        "  {} {}",
+        "Total files:".bright_white(),
+        stats_clone.total_files.to_string().bright_yellow()
+    );
+    println!(
+        "  {} {}",
+        format!("✓ {}:", args.mode.done_label()).bright_green(),
+        stats_clone.placed().to_string().bright_green().bold()
+    );
+    println!(
+        "  {} {}",
+        "⊘ Skipped:".bright_yellow(),
+        stats_clone.skipped.to_string().bright_yellow()
+    );
+    if stats_clone.deduplicated > 0 {
+        println!(
+            "  {} {}",
+            "≡ Duplicates removed:".bright_blue(),
+            stats_clone.deduplicated.to_string().bright_blue()
+        );
+    }
+    if stats_clone.pruned > 0 {
+        println!(
+            "  {} {}",
+            "✂ Empty directories removed:".bright_blue(),
+            stats_clone.pruned.to_string().bright_blue()
+        );
+    }
     println!("  {} {}", "✗ Errors:".bright_red(), stats_clone.errors.to_string().bright_red());
 
     let success_rate = if stats_clone.total_files > 0 {
-        (stats_clone.moved as f64 / stats_clone.total_files as f64) * 100.0
+        (stats_clone.placed() as f64 / stats_clone.total_files as f64) * 100.0
     } else {
         0.0
     };
@@ -371,14 +1546,17 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
 
     if elapsed_time.as_secs() > 0 {
         let speed_mbs = total_size_moved as f64 / elapsed_time.as_secs_f64() / 1_048_576.0;
-        let files_per_sec = stats_clone.moved as f64 / elapsed_time.as_secs_f64();
+        let files_per_sec = stats_clone.placed() as f64 / elapsed_time.as_secs_f64();
         println!("  {} {:.2} MB/s", "Speed:".bright_white(), speed_mbs);
         println!("  {} {:.1} files/s", "Throughput:".bright_white(), files_per_sec);
     }
     println!();
 
     println!("{}", "Categories:".bright_cyan().bold());
-    for (category, progress) in &category_progress_clone {
+    for category in registry.names() {
+        let Some(progress) = category_progress_clone.get(&category) else {
+            continue;
+        };
         if progress.count > 0 {
             println!("  {} {} files ({})",
                 category.bright_white(),
@@ -387,222 +1565,236 @@ fn run_interactive_mode(files: Vec<FileInfo>, base_path: &Path, args: &MyOrganiz
             );
         }
     }
+    print_undo_hint(journal, &stats_clone, args);
     println!();
     println!("{}", "═".repeat(60).bright_cyan());
 
     Ok(())
 }
 
-fn scan_directory(dir: &Path, recursive: bool, verbose: bool) -> Result<Vec<FileInfo>> {
-    let mut files = Vec::new();
-
-    if verbose {
-        println!("{}", "Scanning directory...".bright_cyan());
+/// Detect and categorize a single regular file
+pub(crate) fn file_info(
+    path: PathBuf,
+    metadata: &fs::Metadata,
+    registry: &CategoryRegistry,
+    args: &MyOrganizer,
+) -> FileInfo {
+    let (mime_type, detected_by) = detect_mime(&path, args.detect);
+    let exif = read_exif(&path, mime_type.as_deref());
+    let candidate = Candidate::new(&path, mime_type.as_deref(), metadata);
+    let (category, rule) = registry.classify(&candidate, exif.as_ref());
+    let category = category.to_string();
+    let rule = rule.map(|r| r.name.clone());
+    let date = file_date(
+        &path,
+        metadata,
+        mime_type.as_deref(),
+        exif.as_ref(),
+        args.date_from,
+    );
+    let tags = read_tags(&path, mime_type.as_deref());
+
+    FileInfo {
+        path,
+        mime_type,
+        detected_by,
+        category,
+        subdir: PathBuf::new(),
+        rule,
+        project: None,
+        contents: None,
+        size: metadata.len(),
+        date,
+        exif,
+        tags,
     }
+}
 
-    scan_directory_recursive(dir, dir, recursive, &mut files)?;
-
-    if verbose {
-        println!(
-            "Found {} files\n",
-            files.len().to_string().bright_green().bold()
-        );
-    }
+/// Categorize a MIME type using the built-in categories
+pub fn categorize_file(mime_type: &Option<String>) -> String {
+    CategoryRegistry::default()
+        .categorize(Path::new(""), mime_type.as_deref(), None)
+        .to_string()
+}
 
-    Ok(files)
+pub(crate) fn move_file(
+    file_info: &FileInfo,
+    base_path: &Path,
+    registry: &CategoryRegistry,
+    journal: &mut Journal,
+    args: &MyOrganizer,
+    conflict: &str,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<MoveOutcome> {
+    let action = plan_action(
+        file_info,
+        base_path,
+        registry,
+        conflict,
+        args.mode,
+        &HashSet::new(),
+    )?;
+    apply_action(
+        &action,
+        args.mode,
+        journal,
+        trash(args)?.as_ref(),
+        args.dry_run,
+        progress,
+    )
 }
 
-fn scan_directory_recursive(
-    base_dir: &Path,
-    current_dir: &Path,
-    recursive: bool,
-    files: &mut Vec<FileInfo>,
-) -> Result<()> {
-    for entry in fs::read_dir(current_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = entry.metadata()?;
-
-        if metadata.is_file() {
-            let mime_type = mime_guess::from_path(&path).first().map(|m| m.to_string());
-            let category = categorize_file(&mime_type);
-            let size = metadata.len();
-
-            files.push(FileInfo {
-                path,
-                mime_type,
-                category,
-                size,
-            });
-        } else if metadata.is_dir() && recursive {
-            // Don't recurse into category folders we create
-            if let Some(dir_name) = path.file_name() {
-                let dir_str = dir_name.to_string_lossy();
-                if !["Multimedia", "Docs", "Compressed", "Misc"].contains(&dir_str.as_ref())
-                {
-                    scan_directory_recursive(base_dir, &path, recursive, files)?;
-                }
-            }
-        }
+/// The strategy for a conflict `file_info` runs into, if any: `--conflict`,
+/// unless that is `ask`, which asks through `prompt` (only for real runs, a
+/// dry run reports such conflicts as skipped)
+pub(crate) fn conflict_strategy<'a>(
+    file_info: &FileInfo,
+    base_path: &Path,
+    registry: &CategoryRegistry,
+    args: &'a MyOrganizer,
+    asker: &mut Asker,
+    prompt: impl FnOnce(&Clash) -> io::Result<Choice>,
+) -> Result<&'a str> {
+    if args.dry_run {
+        return Ok(&args.conflict);
     }
+    let destination = base_path.join(destination_path(file_info, registry)?);
+    Ok(asker.strategy(&args.conflict, &file_info.path, &destination, prompt)?)
+}
 
-    Ok(())
+/// Where files replaced or removed as duplicates go, unless `--no-trash`
+fn trash(args: &MyOrganizer) -> Result<Option<Trash>> {
+    if args.no_trash {
+        Ok(None)
+    } else {
+        Trash::home().map(Some)
+    }
 }
 
-pub fn categorize_file(mime_type: &Option<String>) -> String {
-    let mime_categories: HashMap<&str, &str> = [
-        // Images
-        ("image/png", "Multimedia"),
-        ("image/jpeg", "Multimedia"),
-        ("image/jpg", "Multimedia"),
-        ("image/gif", "Multimedia"),
-        ("image/webp", "Multimedia"),
-        ("image/svg+xml", "Multimedia"),
-        ("image/bmp", "Multimedia"),
-        ("image/tiff", "Multimedia"),
-        ("image/x-icon", "Multimedia"),
-        // Audio
-        ("audio/mpeg", "Multimedia"),
-        ("audio/ogg", "Multimedia"),
-        ("audio/wav", "Multimedia"),
-        ("audio/webm", "Multimedia"),
-        ("audio/aac", "Multimedia"),
-        ("audio/flac", "Multimedia"),
-        ("audio/x-m4a", "Multimedia"),
-        // Video
-        ("video/mp4", "Multimedia"),
-        ("video/mpeg", "Multimedia"),
-        ("video/ogg", "Multimedia"),
-        ("video/webm", "Multimedia"),
-        ("video/x-msvideo", "Multimedia"),
-        ("video/x-matroska", "Multimedia"),
-        ("video/quicktime", "Multimedia"),
-        // Archives
-        ("application/zip", "Compressed"),
-        ("application/x-rar-compressed", "Compressed"),
-        ("application/x-7z-compressed", "Compressed"),
-        ("application/gzip", "Compressed"),
-        ("application/x-tar", "Compressed"),
-        ("application/x-bzip", "Compressed"),
-        ("application/x-bzip2", "Compressed"),
-        ("application/x-xz", "Compressed"),
-        // Documents
-        (
-            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-            "Docs",
-        ),
-        (
-            "application/vnd.openxmlformats-officedocument.presentationml.presentation",
-            "Docs",
-        ),
-        (
-            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-            "Docs",
-        ),
-        ("application/vnd.ms-excel", "Docs"),
-        ("application/vnd.ms-powerpoint", "Docs"),
-        ("application/msword", "Docs"),
-        ("application/pdf", "Docs"),
-        ("text/html", "Docs"),
-        ("text/css", "Misc"),
-        ("text/csv", "Docs"),
-        ("text/xml", "Docs"),
-        ("application/xml", "Docs"),
-        ("text/plain", "Docs"),
-        ("text/markdown", "Docs"),
-        ("application/json", "Misc"),
-        ("application/rtf", "Docs"),
-        // Code files (categorized as Misc)
-        ("text/x-python", "Misc"),
-        ("text/x-java", "Misc"),
-        ("text/x-c", "Misc"),
-        ("text/x-c++", "Misc"),
-        ("text/x-rust", "Misc"),
-        ("text/javascript", "Misc"),
-        ("application/javascript", "Misc"),
-        ("application/typescript", "Misc"),
-        ("text/x-go", "Misc"),
-        ("text/x-php", "Misc"),
-        ("text/x-ruby", "Misc"),
-        ("text/x-shellscript", "Misc"),
-    ]
-    .iter()
-    .cloned()
-    .collect();
-
-    match mime_type {
-        Some(mt) => mime_categories
-            .get(mt.as_str())
-            .copied()
-            .unwrap_or("Misc")
-            .to_string(),
-        None => "Misc".to_string(),
-    }
-}
-
-fn move_file(file_info: &FileInfo, base_path: &Path, args: &MyOrganizer) -> Result<bool> {
-    let category_dir = base_path.join(&file_info.category);
-
-    // Create category directory if it doesn't exist
-    if !args.dry_run && !category_dir.exists() {
-        fs::create_dir(&category_dir)?;
-    }
-
-    let file_name = file_info
-        .path
-        .file_name()
-        .ok_or_else(|| OrganizerError::InvalidPath("No filename".to_string()))?;
-
-    let destination = category_dir.join(file_name);
-
-    // Handle conflicts
-    if destination.exists() {
-        match args.conflict.as_str() {
-            "skip" => return Ok(false),
-            "overwrite" => {
-                if !args.dry_run {
-                    fs::remove_file(&destination)?;
-                }
-            }
-            "rename" => {
-                let new_dest = generate_unique_filename(&destination);
-                if !args.dry_run {
-                    fs::rename(&file_info.path, &new_dest)?;
-                }
-                return Ok(true);
-            }
-            _ => return Ok(false),
+/// Where a file goes relative to the organized directory: its category's
+/// template if one applies, otherwise `<category folder>/<file name>`, with
+/// the subdirectory it came from kept according to the registry's layout
+pub fn destination_path(file_info: &FileInfo, registry: &CategoryRegistry) -> Result<PathBuf> {
+    let folder = registry.folder(&file_info.category);
+    let rule_template = file_info
+        .rule
+        .as_deref()
+        .and_then(|name| registry.rule(name))
+        .and_then(|rule| rule.template());
+    let destination = match rule_template.or_else(|| registry.template(&file_info.category)) {
+        // Templates lay out files; a whole directory keeps its own name
+        Some(template) if file_info.contents.is_none() => template.render(file_info, folder),
+        _ => {
+            let file_name = file_info
+                .path
+                .file_name()
+                .ok_or_else(|| OrganizerError::InvalidPath("No filename".to_string()))?;
+            Path::new(folder).join(file_name)
         }
-    }
+    };
+    Ok(registry.layout().apply(destination, &file_info.subdir))
+}
 
-    // Move the file
-    if !args.dry_run {
-        fs::rename(&file_info.path, &destination)?;
+/// A `--flatten-separator` must keep the encoded name a single file name
+fn parse_separator(value: &str) -> std::result::Result<String, String> {
+    if value.is_empty() {
+        return Err("the separator cannot be empty".to_string());
+    }
+    if value.contains(['/', '\\']) {
+        return Err("the separator cannot contain a path separator".to_string());
     }
+    Ok(value.to_string())
+}
 
-    Ok(true)
+pub fn generate_unique_filename(path: &Path) -> Result<PathBuf> {
+    unique_filename(path, path, &RenamePattern::default(), Path::exists)
 }
 
-pub fn generate_unique_filename(path: &Path) -> PathBuf {
-    let parent = path.parent().unwrap();
-    let stem = path.file_stem().unwrap().to_string_lossy();
-    let extension = path.extension().map(|e| e.to_string_lossy());
+/// Most names tried for a file renamed to avoid a conflict
+const MAX_RENAME_ATTEMPTS: usize = 1000;
+
+/// Double extensions kept together when a name gets a suffix, so that
+/// `archive.tar.gz` becomes `archive_1.tar.gz`
+const COMPOUND_EXTENSIONS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz", "tar.lzma", "tar.z",
+];
+
+/// First variant of `path` with `pattern`'s suffix before the extension that
+/// `occupied` does not reject. `source` is the file being renamed, which
+/// fills the `{timestamp}` and `{hash}` placeholders.
+pub(crate) fn unique_filename(
+    path: &Path,
+    source: &Path,
+    pattern: &RenamePattern,
+    occupied: impl Fn(&Path) -> bool,
+) -> Result<PathBuf> {
+    let timestamp = if pattern.uses("timestamp") {
+        fs::metadata(source)
+            .and_then(|m| m.modified())
+            .map(|time| {
+                DateTime::<Local>::from(time)
+                    .format("%Y%m%d-%H%M%S")
+                    .to_string()
+            })
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    // Directories have no contents to hash
+    let hash = if pattern.uses("hash") {
+        hash_file(source)
+            .map(|hash| hash.to_hex()[..8].to_string())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
 
-    let mut counter = 1;
-    loop {
-        let new_name = match &extension {
-            Some(ext) => format!("{}_{}.{}", stem, counter, ext),
-            None => format!("{}_{}", stem, counter),
-        };
+    for attempt in 1..=MAX_RENAME_ATTEMPTS {
+        let new_path = with_suffix(path, &pattern.render(attempt, &timestamp, &hash));
+        if !occupied(&new_path) {
+            return Ok(new_path);
+        }
+    }
+    Err(OrganizerError::NameExhausted(
+        path.to_path_buf(),
+        MAX_RENAME_ATTEMPTS,
+    ))
+}
+
+/// `path` with `suffix` inserted between the stem and the extension
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let new_name = match split_extension(&name) {
+        (stem, Some(ext)) => format!("{}{}.{}", stem, suffix, ext),
+        (stem, None) => format!("{}{}", stem, suffix),
+    };
+    path.with_file_name(new_name)
+}
 
-        let new_path = parent.join(new_name);
-        if !new_path.exists() {
-            return new_path;
+/// Split a file name into its stem and extension, keeping compound extensions
+/// whole. A leading dot starts a hidden name rather than an extension.
+fn split_extension(name: &str) -> (&str, Option<&str>) {
+    let lower = name.to_ascii_lowercase();
+    for compound in COMPOUND_EXTENSIONS {
+        let at = name.len().saturating_sub(compound.len());
+        if at > 1 && lower.ends_with(&format!(".{}", compound)) {
+            return (&name[..at - 1], Some(&name[at..]));
         }
-        counter += 1;
+    }
+    match name.rfind('.') {
+        Some(0) | None => (name, None),
+        Some(dot) => (&name[..dot], Some(&name[dot + 1..])),
     }
 }
 
+/// `path` relative to `base` for display, or the full path if it is outside `base`
+pub(crate) fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
 fn format_size(size: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -619,10 +1811,14 @@ fn format_size(size: u64) -> String {
     }
 }
 
-fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len - 3])
+/// `s` cut to at most `max_len` characters, ending in "..." when shortened
+pub(crate) fn truncate_str(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        return s.to_string();
     }
+    let end = s
+        .char_indices()
+        .nth(max_len.saturating_sub(3))
+        .map_or(s.len(), |(index, _)| index);
+    format!("{}...", &s[..end])
 }
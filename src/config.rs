@@ -0,0 +1,145 @@
+use crate::error::{OrganizerError, Result};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Contents of `config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Category for files no other category claims (defaults to "Misc")
+    pub fallback: Option<String>,
+
+    /// Keep the built-in categories after the user-defined ones (defaults to true)
+    pub builtin: Option<bool>,
+
+    /// Destination template for categories without their own, e.g. `{category}/{year}/{name}.{ext}`
+    pub template: Option<String>,
+
+    /// User-defined categories, matched in declaration order
+    #[serde(default, rename = "category")]
+    pub categories: Vec<CategoryConfig>,
+
+    /// Rules tried in declaration order before any category, first match wins
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// A single `[[category]]` table
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CategoryConfig {
+    pub name: String,
+
+    /// Folder created for this category (defaults to the name)
+    pub folder: Option<String>,
+
+    /// MIME types, either exact (`application/pdf`) or wildcards (`image/*`)
+    #[serde(default)]
+    pub mime: Vec<String>,
+
+    /// File extensions without the leading dot (`pdf`, `tar.gz`)
+    #[serde(default)]
+    pub extensions: Vec<String>,
+
+    /// Only match images with (`true`) or without (`false`) a camera make/model in their EXIF
+    pub camera: Option<bool>,
+
+    /// Only match images with (`true`) or without (`false`) GPS coordinates in their EXIF
+    pub gps: Option<bool>,
+
+    /// Destination template for files in this category
+    pub template: Option<String>,
+}
+
+/// A single `[[rule]]` table. Every condition given must hold for the rule to match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Name shown by `--explain` (defaults to "rule N")
+    pub name: Option<String>,
+
+    /// Category the matching files go to; it is created if no category has this name
+    pub category: String,
+
+    /// Destination template for matching files, overriding the category's
+    pub template: Option<String>,
+
+    /// File extensions without the leading dot, any of which matches
+    #[serde(default)]
+    pub extensions: Vec<String>,
+
+    /// MIME types, exact or wildcards, any of which matches
+    #[serde(default)]
+    pub mime: Vec<String>,
+
+    /// Regular expression the file name must match, e.g. `(?i)invoice`
+    pub filename: Option<String>,
+
+    /// Smallest matching size, e.g. `10MB`
+    pub min_size: Option<String>,
+
+    /// Largest matching size, e.g. `1GB`
+    pub max_size: Option<String>,
+
+    /// Only match files last modified longer ago than this, e.g. `30d`
+    pub older_than: Option<String>,
+
+    /// Only match files last modified more recently than this, e.g. `12h`
+    pub newer_than: Option<String>,
+
+    /// Glob the name of the directory holding the file must match, e.g. `Scans*`
+    pub parent: Option<String>,
+}
+
+impl Config {
+    /// Load the config from `path`, or from the default location when no path is given.
+    ///
+    /// A missing default config is not an error; a missing explicit one is.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                if !path.exists() {
+                    return Err(OrganizerError::PathNotFound(path.to_path_buf()));
+                }
+                Self::from_file(path)
+            }
+            None => match default_config_path() {
+                Some(path) if path.is_file() => Self::from_file(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| OrganizerError::Config(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| OrganizerError::Config(e.to_string()))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/organizer/config.toml`, falling back to `~/.config/organizer/config.toml`
+pub fn default_config_path() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config").map(|dir| dir.join("organizer").join("config.toml"))
+}
+
+/// Resolve an XDG base directory from its environment variable or `~/<fallback>`
+pub fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(fallback)))
+}
+
+pub fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .filter(|h| !h.is_empty())
+        .map(PathBuf::from)
+}
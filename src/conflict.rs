@@ -0,0 +1,174 @@
+use crate::hash::hash_file;
+use chrono::{DateTime, Local, NaiveDateTime};
+use colored::Colorize;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+/// Strategies a `--conflict ask` prompt offers, with the key that picks each
+/// for one file; the capital letter picks it for every later conflict too
+pub const ASK_CHOICES: [(char, &str); 6] = [
+    ('s', "skip"),
+    ('o', "overwrite"),
+    ('r', "rename"),
+    ('d', "dedupe"),
+    ('n', "newer"),
+    ('l', "larger"),
+];
+
+/// The answer to a conflict prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Choice {
+    pub strategy: &'static str,
+    /// Settle every later conflict of the run the same way without asking
+    pub apply_to_all: bool,
+}
+
+impl Choice {
+    /// The choice a key stands for, if any
+    pub fn from_key(key: char) -> Option<Self> {
+        ASK_CHOICES
+            .iter()
+            .find(|(choice, _)| *choice == key.to_ascii_lowercase())
+            .map(|(_, strategy)| Choice {
+                strategy,
+                apply_to_all: key.is_ascii_uppercase(),
+            })
+    }
+}
+
+/// One of the two files of a conflict, as a prompt shows it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Side {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<NaiveDateTime>,
+    /// BLAKE3 hash of the contents, unless it can't be read or is a directory
+    pub hash: Option<String>,
+}
+
+impl Side {
+    pub fn read(path: &Path) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            path: path.to_path_buf(),
+            size: metadata.as_ref().map_or(0, |m| m.len()),
+            modified: metadata
+                .as_ref()
+                .and_then(|m| m.modified().ok())
+                .map(|time| DateTime::<Local>::from(time).naive_local()),
+            hash: metadata
+                .filter(|m| m.is_file())
+                .and_then(|_| hash_file(path).ok())
+                .map(|hash| hash.to_hex().to_string()),
+        }
+    }
+
+    /// e.g. "2048 bytes, modified 2024-05-01 14:03, blake3 af1349b9f5f9a1a6"
+    pub fn describe(&self) -> String {
+        let modified = self.modified.map_or("unknown".to_string(), |m| {
+            m.format("%Y-%m-%d %H:%M").to_string()
+        });
+        let hash = self.hash.as_deref().map_or("-", |h| &h[..16]);
+        format!(
+            "{} bytes, modified {}, blake3 {}",
+            self.size, modified, hash
+        )
+    }
+}
+
+/// A file about to be put where another one already is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clash {
+    pub source: Side,
+    pub destination: Side,
+}
+
+impl Clash {
+    pub fn read(source: &Path, destination: &Path) -> Self {
+        Self {
+            source: Side::read(source),
+            destination: Side::read(destination),
+        }
+    }
+
+    /// Whether both files have the same contents
+    pub fn identical(&self) -> bool {
+        self.source.hash.is_some() && self.source.hash == self.destination.hash
+    }
+}
+
+/// Settles conflicts for `--conflict ask`, remembering an answer that applies
+/// to all of them
+#[derive(Debug, Default)]
+pub struct Asker {
+    remembered: Option<&'static str>,
+}
+
+impl Asker {
+    /// The strategy for putting `source` at `destination`: `conflict` itself,
+    /// unless it is `ask` and `destination` is taken, in which case the
+    /// remembered answer or the one `prompt` gives
+    pub fn strategy<'a>(
+        &mut self,
+        conflict: &'a str,
+        source: &Path,
+        destination: &Path,
+        prompt: impl FnOnce(&Clash) -> io::Result<Choice>,
+    ) -> io::Result<&'a str> {
+        if conflict != "ask" || fs::symlink_metadata(destination).is_err() {
+            return Ok(conflict);
+        }
+        if let Some(strategy) = self.remembered {
+            return Ok(strategy);
+        }
+        let choice = prompt(&Clash::read(source, destination))?;
+        if choice.apply_to_all {
+            self.remembered = Some(choice.strategy);
+        }
+        Ok(choice.strategy)
+    }
+}
+
+/// Ask on the terminal, repeating the question until it gets a valid answer.
+/// Without any input left, the rest of the conflicts are skipped.
+pub fn prompt_terminal(clash: &Clash) -> io::Result<Choice> {
+    println!();
+    println!(
+        "{} {} already exists",
+        "?".bright_yellow().bold(),
+        clash.destination.path.display().to_string().bright_cyan()
+    );
+    println!("  existing: {}", clash.destination.describe());
+    println!("  incoming: {}", clash.source.describe());
+    if clash.identical() {
+        println!("  {}", "The files are identical".bright_green());
+    }
+
+    let options = ASK_CHOICES
+        .iter()
+        .map(|(key, strategy)| format!("[{}]{}", key, &strategy[1..]))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let stdin = io::stdin();
+    loop {
+        print!("  {} (capital letter: apply to all) > ", options);
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if stdin.lock().read_line(&mut answer)? == 0 {
+            println!();
+            return Ok(Choice {
+                strategy: "skip",
+                apply_to_all: true,
+            });
+        }
+        let mut keys = answer.trim().chars();
+        if let (Some(key), None) = (keys.next(), keys.next()) {
+            if let Some(choice) = Choice::from_key(key) {
+                return Ok(choice);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use clap::ValueEnum;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Bytes read from the start of a file for sniffing
+const HEADER_LEN: usize = 8192;
+
+/// How file types are detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DetectMode {
+    /// Guess from the file extension only
+    Extension,
+    /// Sniff the file header only
+    Content,
+    /// Sniff the file header, falling back to the extension
+    Both,
+}
+
+/// Which method produced a file's MIME type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionMethod {
+    Extension,
+    Content,
+    Unknown,
+}
+
+impl fmt::Display for DetectionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectionMethod::Extension => write!(f, "extension"),
+            DetectionMethod::Content => write!(f, "content"),
+            DetectionMethod::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Detect the MIME type of a file and record which method found it
+pub fn detect_mime(path: &Path, mode: DetectMode) -> (Option<String>, DetectionMethod) {
+    let from_extension = || mime_guess::from_path(path).first().map(|m| m.to_string());
+
+    let result = match mode {
+        DetectMode::Extension => from_extension().map(|m| (m, DetectionMethod::Extension)),
+        DetectMode::Content => sniff_file(path).map(|m| (m.to_string(), DetectionMethod::Content)),
+        DetectMode::Both => match (sniff_file(path), from_extension()) {
+            // Zip is the container for many formats (jar, apk, whl...), and an
+            // OLE compound file whose directory lies past the header could be
+            // any legacy Office document; a specific extension says more than
+            // the generic container type.
+            (Some("application/zip" | "application/x-ole-storage"), Some(ext)) => {
+                Some((ext, DetectionMethod::Extension))
+            }
+            (Some(sniffed), _) => Some((sniffed.to_string(), DetectionMethod::Content)),
+            (None, Some(ext)) => Some((ext, DetectionMethod::Extension)),
+            (None, None) => None,
+        },
+    };
+
+    match result {
+        Some((mime, method)) => (Some(mime), method),
+        None => (None, DetectionMethod::Unknown),
+    }
+}
+
+/// Sniff a file's MIME type from its header, ignoring unreadable files
+pub fn sniff_file(path: &Path) -> Option<&'static str> {
+    read_header(path)
+        .ok()
+        .and_then(|header| sniff_bytes(&header))
+}
+
+fn read_header(path: &Path) -> io::Result<Vec<u8>> {
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?
+        .take(HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
+/// Identify a format from its magic number
+pub fn sniff_bytes(buf: &[u8]) -> Option<&'static str> {
+    let at = |offset: usize, magic: &[u8]| buf.get(offset..offset + magic.len()) == Some(magic);
+
+    // Images
+    if at(0, b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if at(0, b"\xff\xd8\xff") {
+        return Some("image/jpeg");
+    }
+    if at(0, b"GIF87a") || at(0, b"GIF89a") {
+        return Some("image/gif");
+    }
+    if at(0, b"II*\0") || at(0, b"MM\0*") {
+        return Some("image/tiff");
+    }
+    if at(0, b"BM") && buf.len() >= 18 {
+        // The DIB header size distinguishes real bitmaps from text starting with "BM"
+        let dib_size = u32::from_le_bytes([buf[14], buf[15], buf[16], buf[17]]);
+        if [12, 40, 52, 56, 64, 108, 124].contains(&dib_size) {
+            return Some("image/bmp");
+        }
+    }
+    if at(0, b"\0\0\x01\0") && buf.len() >= 6 && buf[4] != 0 {
+        return Some("image/x-icon");
+    }
+
+    // RIFF containers
+    if at(0, b"RIFF") {
+        if at(8, b"WEBP") {
+            return Some("image/webp");
+        }
+        if at(8, b"WAVE") {
+            return Some("audio/wav");
+        }
+        if at(8, b"AVI ") {
+            return Some("video/x-msvideo");
+        }
+    }
+
+    // ISO base media (MP4, MOV, M4A, HEIC)
+    if at(4, b"ftyp") && buf.len() >= 12 {
+        return Some(match &buf[8..12] {
+            b"heic" | b"heix" | b"hevc" | b"mif1" | b"msf1" => "image/heic",
+            b"avif" | b"avis" => "image/avif",
+            b"M4A " | b"M4B " => "audio/x-m4a",
+            b"qt  " => "video/quicktime",
+            _ => "video/mp4",
+        });
+    }
+
+    // Audio
+    if at(0, b"ID3") || (buf.len() >= 2 && buf[0] == 0xff && matches!(buf[1], 0xfb | 0xf3 | 0xf2)) {
+        return Some("audio/mpeg");
+    }
+    if at(0, b"fLaC") {
+        return Some("audio/flac");
+    }
+    if at(0, b"OggS") {
+        return Some(if contains(buf, b"\x80theora") {
+            "video/ogg"
+        } else {
+            "audio/ogg"
+        });
+    }
+
+    // Video
+    if at(0, b"\x1a\x45\xdf\xa3") {
+        return Some(if contains(buf, b"webm") {
+            "video/webm"
+        } else {
+            "video/x-matroska"
+        });
+    }
+    if at(0, b"\0\0\x01\xba") || at(0, b"\0\0\x01\xb3") {
+        return Some("video/mpeg");
+    }
+
+    // Documents
+    if at(0, b"%PDF-") {
+        return Some("application/pdf");
+    }
+    if at(0, b"{\\rtf") {
+        return Some("application/rtf");
+    }
+    if at(0, b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1") {
+        return Some(sniff_ole(buf));
+    }
+
+    // Archives
+    if at(0, b"PK\x03\x04") {
+        return Some(sniff_zip(buf));
+    }
+    if at(0, b"Rar!\x1a\x07") {
+        return Some("application/x-rar-compressed");
+    }
+    if at(0, b"7z\xbc\xaf\x27\x1c") {
+        return Some("application/x-7z-compressed");
+    }
+    if at(0, b"\x1f\x8b") {
+        return Some("application/gzip");
+    }
+    if at(0, b"BZh") {
+        return Some("application/x-bzip2");
+    }
+    if at(0, b"\xfd7zXZ\0") {
+        return Some("application/x-xz");
+    }
+    if at(257, b"ustar") {
+        return Some("application/x-tar");
+    }
+
+    None
+}
+
+/// Tell office formats and other zip-based documents apart from plain archives
+fn sniff_zip(buf: &[u8]) -> &'static str {
+    // EPUB and OpenDocument store an uncompressed `mimetype` entry first
+    if buf.get(30..38) == Some(b"mimetype") {
+        let len = u32::from_le_bytes([buf[18], buf[19], buf[20], buf[21]]) as usize;
+        // A length that overflows can't fit in the buffer either
+        if let Some(mime) = 38usize.checked_add(len).and_then(|end| buf.get(38..end)) {
+            match mime {
+                b"application/epub+zip" => return "application/epub+zip",
+                b"application/vnd.oasis.opendocument.text" => {
+                    return "application/vnd.oasis.opendocument.text"
+                }
+                b"application/vnd.oasis.opendocument.spreadsheet" => {
+                    return "application/vnd.oasis.opendocument.spreadsheet"
+                }
+                b"application/vnd.oasis.opendocument.presentation" => {
+                    return "application/vnd.oasis.opendocument.presentation"
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Office Open XML names its parts after the application
+    if contains(buf, b"[Content_Types].xml") || contains(buf, b"_rels/.rels") {
+        if contains(buf, b"word/") {
+            return "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+        }
+        if contains(buf, b"xl/") {
+            return "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+        }
+        if contains(buf, b"ppt/") {
+            return "application/vnd.openxmlformats-officedocument.presentationml.presentation";
+        }
+    }
+
+    "application/zip"
+}
+
+/// Identify legacy Office documents by the stream names in the compound file directory
+fn sniff_ole(buf: &[u8]) -> &'static str {
+    let utf16 =
+        |name: &str| -> Vec<u8> { name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect() };
+
+    if contains(buf, &utf16("WordDocument")) {
+        "application/msword"
+    } else if contains(buf, &utf16("Workbook")) || contains(buf, &utf16("Book")) {
+        "application/vnd.ms-excel"
+    } else if contains(buf, &utf16("PowerPoint Document")) {
+        "application/vnd.ms-powerpoint"
+    } else {
+        "application/x-ole-storage"
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|w| w == needle)
+}
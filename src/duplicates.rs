@@ -0,0 +1,243 @@
+use crate::cli::{unique_filename, FileInfo};
+use crate::error::{OrganizerError, Result};
+use crate::hash::hash_file;
+use crate::journal::{ConflictAction, Journal};
+use crate::template::RenamePattern;
+use crate::transfer::rename_or_copy;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Folder extra copies are moved into
+pub const DUPLICATES_FOLDER: &str = "Duplicates";
+
+/// Which copy of a duplicate group to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeepPolicy {
+    /// The copy with the oldest modification time
+    Oldest,
+    /// The copy with the newest modification time
+    Newest,
+    /// The copy with the shortest path
+    ShortestPath,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateFile {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+/// Files that share the same size and content hash
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+impl DuplicateGroup {
+    /// The copy to keep under `policy`; ties are broken by path so results are stable
+    pub fn keeper(&self, policy: KeepPolicy) -> &Path {
+        let by_path = |a: &&DuplicateFile, b: &&DuplicateFile| a.path.cmp(&b.path);
+        let keeper = match policy {
+            KeepPolicy::Oldest => self
+                .files
+                .iter()
+                .min_by(|a, b| a.modified.cmp(&b.modified).then_with(|| by_path(a, b))),
+            KeepPolicy::Newest => self
+                .files
+                .iter()
+                .max_by(|a, b| a.modified.cmp(&b.modified).then_with(|| by_path(b, a))),
+            KeepPolicy::ShortestPath => self.files.iter().min_by(|a, b| {
+                let len = |f: &DuplicateFile| f.path.as_os_str().len();
+                len(a).cmp(&len(b)).then_with(|| by_path(a, b))
+            }),
+        };
+        keeper.map(|f| f.path.as_path()).unwrap_or(Path::new(""))
+    }
+
+    /// Bytes that would be reclaimed by keeping a single copy
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64 - 1)
+    }
+}
+
+/// Group files by size, then by content hash, keeping only groups with more than one file.
+///
+/// Empty files, symbolic links and whole directories are ignored and files that
+/// cannot be read are skipped.
+pub fn find_duplicates(files: &[FileInfo]) -> Vec<DuplicateGroup> {
+    let mut by_size: HashMap<u64, Vec<&FileInfo>> = HashMap::new();
+    let candidates = files
+        .iter()
+        .filter(|f| f.size > 0 && f.contents.is_none() && !f.path.is_symlink());
+    for file in candidates {
+        by_size.entry(file.size).or_default().push(file);
+    }
+
+    let mut groups = Vec::new();
+    for (size, candidates) in by_size {
+        if candidates.len() < 2 {
+            continue;
+        }
+
+        let mut by_hash: HashMap<blake3::Hash, Vec<DuplicateFile>> = HashMap::new();
+        for file in candidates {
+            if let Ok(hash) = hash_file(&file.path) {
+                by_hash.entry(hash).or_default().push(DuplicateFile {
+                    path: file.path.clone(),
+                    modified: fs::metadata(&file.path).and_then(|m| m.modified()).ok(),
+                });
+            }
+        }
+
+        for (hash, mut files) in by_hash {
+            if files.len() > 1 {
+                files.sort_by(|a, b| a.path.cmp(&b.path));
+                groups.push(DuplicateGroup {
+                    hash: hash.to_hex().to_string(),
+                    size,
+                    files,
+                });
+            }
+        }
+    }
+
+    // Biggest savings first
+    groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.hash.cmp(&b.hash))
+    });
+    groups
+}
+
+/// Move every copy but the keeper into `<base_path>/Duplicates`, renaming on name clashes.
+///
+/// Returns each extra copy with the path it was (or in a dry run would be) moved to.
+pub fn move_extras(
+    groups: &[DuplicateGroup],
+    policy: KeepPolicy,
+    base_path: &Path,
+    journal: &mut Journal,
+    dry_run: bool,
+) -> Vec<(PathBuf, Result<PathBuf>)> {
+    let folder = base_path.join(DUPLICATES_FOLDER);
+    let mut results = Vec::new();
+    // Names given out so far, which a dry run never puts on disk
+    let mut claimed = HashSet::new();
+
+    for group in groups {
+        let keeper = group.keeper(policy);
+        for dup in group.files.iter().filter(|f| f.path != keeper) {
+            let result = move_to_folder(&dup.path, &folder, journal, &claimed, dry_run);
+            if let Ok(destination) = &result {
+                claimed.insert(destination.clone());
+            }
+            results.push((dup.path.clone(), result));
+        }
+    }
+
+    results
+}
+
+fn move_to_folder(
+    path: &Path,
+    folder: &Path,
+    journal: &mut Journal,
+    claimed: &HashSet<PathBuf>,
+    dry_run: bool,
+) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| OrganizerError::InvalidPath("No filename".to_string()))?;
+
+    if !dry_run && !folder.exists() {
+        fs::create_dir(folder)?;
+        journal.record_create_dir(folder)?;
+    }
+
+    let occupied = |p: &Path| fs::symlink_metadata(p).is_ok() || claimed.contains(p);
+    let mut destination = folder.join(file_name);
+    if occupied(&destination) {
+        destination = unique_filename(&destination, path, &RenamePattern::default(), occupied)?;
+    }
+    if !dry_run {
+        rename_or_copy(path, &destination, &mut |_, _| {})?;
+        let conflict = if destination.file_name() == Some(file_name) {
+            ConflictAction::None
+        } else {
+            ConflictAction::Rename
+        };
+        journal.record_move(path, &destination, conflict)?;
+    }
+    Ok(destination)
+}
+
+#[derive(Serialize)]
+struct ExportGroup<'a> {
+    hash: &'a str,
+    size: u64,
+    keep: Option<&'a Path>,
+    files: Vec<&'a Path>,
+}
+
+/// Write the groups as JSON or CSV, chosen by the file extension
+pub fn export(groups: &[DuplicateGroup], keep: Option<KeepPolicy>, path: &Path) -> Result<()> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+    if !matches!(extension.as_deref(), Some("json" | "csv")) {
+        return Err(OrganizerError::InvalidPath(format!(
+            "{}: export format must be .json or .csv",
+            path.display()
+        )));
+    }
+
+    let mut file = fs::File::create(path)?;
+    if extension.as_deref() == Some("json") {
+        let records: Vec<ExportGroup> = groups
+            .iter()
+            .map(|group| ExportGroup {
+                hash: &group.hash,
+                size: group.size,
+                keep: keep.map(|policy| group.keeper(policy)),
+                files: group.files.iter().map(|f| f.path.as_path()).collect(),
+            })
+            .collect();
+        serde_json::to_writer_pretty(&mut file, &records).map_err(io::Error::from)?;
+        writeln!(file)?;
+    } else {
+        writeln!(file, "group,hash,size,path,keep")?;
+        for (index, group) in groups.iter().enumerate() {
+            let keeper = keep.map(|policy| group.keeper(policy));
+            for dup in &group.files {
+                writeln!(
+                    file,
+                    "{},{},{},{},{}",
+                    index + 1,
+                    group.hash,
+                    group.size,
+                    csv_field(&dup.path.to_string_lossy()),
+                    keeper == Some(dup.path.as_path())
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -7,6 +7,14 @@ pub enum OrganizerError {
     PathNotFound(PathBuf),
     PathNotDirectory(PathBuf),
     InvalidPath(String),
+    Config(String),
+    Journal(String),
+    Watch(String),
+    Template(String),
+    Plan(String),
+    Pattern(String),
+    Trash(String),
+    NameExhausted(PathBuf, usize),
 }
 
 impl fmt::Display for OrganizerError {
@@ -20,6 +28,21 @@ impl fmt::Display for OrganizerError {
                 write!(f, "Path is not a directory: {}", path.display())
             }
             OrganizerError::InvalidPath(msg) => write!(f, "Invalid path: {}", msg),
+            OrganizerError::Config(msg) => write!(f, "Config error: {}", msg),
+            OrganizerError::Journal(msg) => write!(f, "Journal error: {}", msg),
+            OrganizerError::Watch(msg) => write!(f, "Watch error: {}", msg),
+            OrganizerError::Template(msg) => write!(f, "Template error: {}", msg),
+            OrganizerError::Plan(msg) => write!(f, "Plan error: {}", msg),
+            OrganizerError::Pattern(msg) => write!(f, "Invalid pattern: {}", msg),
+            OrganizerError::Trash(msg) => write!(f, "Trash error: {}", msg),
+            OrganizerError::NameExhausted(path, attempts) => {
+                write!(
+                    f,
+                    "No free name for {} after {} attempts",
+                    path.display(),
+                    attempts
+                )
+            }
         }
     }
 }
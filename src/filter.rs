@@ -0,0 +1,358 @@
+use crate::error::{OrganizerError, Result};
+use clap::ValueEnum;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::{
+    fs::Metadata,
+    path::{Component, Path},
+    time::{Duration, SystemTime},
+};
+
+/// Per-directory file of gitignore-style patterns for files the scan leaves alone
+pub const IGNORE_FILE: &str = ".organizerignore";
+
+/// Why a scan left a file out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filtered {
+    /// An include or exclude glob or an ignore file
+    Pattern,
+    /// `--min-size` or `--max-size`
+    Size,
+    /// `--older-than` or `--newer-than`
+    Age,
+    /// A hidden file, or one that isn't hidden under `--hidden only`
+    Hidden,
+    /// A project directory left alone by `--projects skip`
+    Project,
+}
+
+/// What to do with hidden files and directories, whose names start with a dot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HiddenMode {
+    /// Organize hidden files like any other
+    Include,
+    /// Leave hidden files where they are and don't scan hidden directories
+    #[default]
+    Exclude,
+    /// Only organize hidden files and files inside hidden directories
+    Only,
+}
+
+/// Number of files (or whole project directories) a scan left out, by reason
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilteredCounts {
+    pub pattern: usize,
+    pub size: usize,
+    pub age: usize,
+    pub hidden: usize,
+    pub projects: usize,
+}
+
+impl FilteredCounts {
+    pub fn add(&mut self, reason: Filtered) {
+        match reason {
+            Filtered::Pattern => self.pattern += 1,
+            Filtered::Size => self.size += 1,
+            Filtered::Age => self.age += 1,
+            Filtered::Hidden => self.hidden += 1,
+            Filtered::Project => self.projects += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.pattern + self.size + self.age + self.hidden + self.projects
+    }
+
+    /// Breakdown such as "2 by size, 1 hidden", leaving out reasons with no files
+    pub fn describe(&self) -> String {
+        [
+            (self.pattern, "by pattern"),
+            (self.size, "by size"),
+            (self.age, "by age"),
+            (self.hidden, "hidden"),
+            (self.projects, "project directories"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// `--include` and `--exclude` globs, matched against paths relative to the
+/// organized directory, the size and age limits on candidate files, and the
+/// hidden file and depth settings
+#[derive(Debug, Clone)]
+pub struct ScanFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    older_than: Option<Duration>,
+    newer_than: Option<Duration>,
+    hidden: HiddenMode,
+    max_depth: Option<usize>,
+    now: SystemTime,
+}
+
+impl ScanFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: if include.is_empty() {
+                None
+            } else {
+                Some(glob_set(include)?)
+            },
+            exclude: glob_set(exclude)?,
+            min_size: None,
+            max_size: None,
+            older_than: None,
+            newer_than: None,
+            hidden: HiddenMode::default(),
+            max_depth: None,
+            now: SystemTime::now(),
+        })
+    }
+
+    /// Only accept files of at least `min` and at most `max` bytes
+    pub fn with_size(mut self, min: Option<u64>, max: Option<u64>) -> Self {
+        self.min_size = min;
+        self.max_size = max;
+        self
+    }
+
+    /// Only accept files last modified more than `older_than` and less than
+    /// `newer_than` ago
+    pub fn with_age(mut self, older_than: Option<Duration>, newer_than: Option<Duration>) -> Self {
+        self.older_than = older_than;
+        self.newer_than = newer_than;
+        self
+    }
+
+    /// Measure ages from the current time again, for a filter that outlives a
+    /// single scan
+    pub fn refresh_now(&mut self) {
+        self.now = SystemTime::now();
+    }
+
+    pub fn with_hidden(mut self, hidden: HiddenMode) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Only organize files at most `max_depth` directories below the organized one
+    pub fn with_max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Whether a file or directory passes the `--hidden` setting. Under
+    /// `--hidden only` every directory is scanned, as it may hold hidden files.
+    pub fn allows_hidden(&self, relative: &Path, is_dir: bool) -> bool {
+        match self.hidden {
+            HiddenMode::Include => true,
+            HiddenMode::Exclude => !is_hidden(relative),
+            HiddenMode::Only => is_dir || is_hidden(relative),
+        }
+    }
+
+    /// Whether files directly inside `relative_dir` are within `--max-depth`
+    pub fn within_depth(&self, relative_dir: &Path) -> bool {
+        self.max_depth
+            .is_none_or(|max| relative_dir.components().count() <= max)
+    }
+
+    /// The size or age limit a file falls outside of, if any
+    pub fn check_limits(&self, metadata: &Metadata) -> Option<Filtered> {
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return Some(Filtered::Size);
+        }
+
+        if self.older_than.is_some() || self.newer_than.is_some() {
+            // Files with a modification time in the future count as brand new
+            let age = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| self.now.duration_since(modified).ok())
+                .unwrap_or_default();
+            if self.older_than.is_some_and(|limit| age <= limit)
+                || self.newer_than.is_some_and(|limit| age >= limit)
+            {
+                return Some(Filtered::Age);
+            }
+        }
+        None
+    }
+
+    /// Whether a file is organized: it matches an include pattern (if any
+    /// were given) and no exclude pattern
+    pub fn allows_file(&self, relative: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|set| set.is_match(relative))
+            && !self.exclude.is_match(relative)
+    }
+
+    /// Whether a directory is scanned at all. Include patterns only select
+    /// files, so only exclude patterns can prune a directory.
+    pub fn allows_dir(&self, relative: &Path) -> bool {
+        !self.exclude.is_match(relative)
+    }
+
+    /// Whether a file found outside a scan would have been reached and
+    /// organized by one: neither it nor any directory above it is filtered out
+    pub fn allows_path(&self, relative: &Path) -> bool {
+        let mut dirs = relative.ancestors().skip(1);
+        self.allows_file(relative)
+            && self.allows_hidden(relative, false)
+            && self.within_depth(relative.parent().unwrap_or(Path::new("")))
+            && dirs.all(|dir| dir.as_os_str().is_empty() || self.allows_dir(dir))
+    }
+}
+
+/// Whether the path or any directory in it has a name starting with a dot
+fn is_hidden(relative: &Path) -> bool {
+    relative.components().any(|c| match c {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Parse a size such as `500MB`, `1.5G` or `4096` (bytes). Units are powers
+/// of 1024, matching how sizes are displayed.
+pub fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let (number, unit) = split_unit(value)?;
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => {
+            return Err(format!(
+                "unknown size unit '{}' (expected B, KB, MB, GB or TB)",
+                unit
+            ))
+        }
+    };
+    let bytes = number * multiplier as f64;
+    // u64::MAX rounds up to 2^64 as a float, so this rejects every value a cast would clamp
+    if !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(format!("'{}' is too large", value.trim()));
+    }
+    Ok(bytes as u64)
+}
+
+/// Parse an age such as `7d`, `12h`, `2w` or `90min`
+pub fn parse_age(value: &str) -> std::result::Result<Duration, String> {
+    let (number, unit) = split_unit(value)?;
+    let seconds: u64 = match unit.to_ascii_lowercase().as_str() {
+        "s" | "sec" | "secs" => 1,
+        "m" | "min" | "mins" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => 24 * 60 * 60,
+        "w" | "week" | "weeks" => 7 * 24 * 60 * 60,
+        "y" | "year" | "years" => 365 * 24 * 60 * 60,
+        "" => return Err("missing unit (expected s, min, h, d, w or y, e.g. 7d)".to_string()),
+        _ => {
+            return Err(format!(
+                "unknown age unit '{}' (expected s, min, h, d, w or y)",
+                unit
+            ))
+        }
+    };
+    Duration::try_from_secs_f64(number * seconds as f64)
+        .map_err(|_| format!("'{}' is too long", value.trim()))
+}
+
+/// Split `1.5GB` into `1.5` and `GB`
+fn split_unit(value: &str) -> std::result::Result<(f64, &str), String> {
+    let value = value.trim();
+    let end = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number: f64 = value[..end]
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", value))?;
+    Ok((number, value[end..].trim()))
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // `keep/` names a directory, which is matched by its path like any other
+        let trimmed = pattern.trim_end_matches('/');
+        let glob = Glob::new(trimmed)
+            .map_err(|e| OrganizerError::Pattern(format!("'{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| OrganizerError::Pattern(e.to_string()))
+}
+
+/// The `.organizerignore` files of the directories from the organized
+/// directory down to the one being scanned; deeper files take precedence.
+#[derive(Default)]
+pub struct IgnoreStack {
+    levels: Vec<Option<Gitignore>>,
+}
+
+impl IgnoreStack {
+    /// Load the ignore file of a directory the scan is entering, if it has one
+    pub fn enter(&mut self, dir: &Path) -> Result<()> {
+        let path = dir.join(IGNORE_FILE);
+        let ignore = if path.is_file() {
+            let mut builder = GitignoreBuilder::new(dir);
+            if let Some(e) = builder.add(&path) {
+                return Err(OrganizerError::Pattern(e.to_string()));
+            }
+            Some(
+                builder
+                    .build()
+                    .map_err(|e| OrganizerError::Pattern(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+        self.levels.push(ignore);
+        Ok(())
+    }
+
+    /// Forget the ignore file of the directory the scan just left
+    pub fn leave(&mut self) {
+        self.levels.pop();
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for ignore in self.levels.iter().rev().flatten() {
+            match ignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    /// Ignore files that apply to `path`, loaded from `base_path` down to its
+    /// parent, for checking single files outside a scan
+    pub fn for_path(base_path: &Path, path: &Path) -> Result<Self> {
+        let mut stack = Self::default();
+        stack.enter(base_path)?;
+        let relative = path.strip_prefix(base_path).unwrap_or(path);
+        let mut dir = base_path.to_path_buf();
+        if let Some(parent) = relative.parent() {
+            for component in parent.components() {
+                dir.push(component);
+                stack.enter(&dir)?;
+            }
+        }
+        Ok(stack)
+    }
+}
@@ -0,0 +1,72 @@
+use crate::categories::CategoryRegistry;
+use crate::detect::{detect_mime, DetectMode};
+use crate::rules::Candidate;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Category for directories moved whole by `--dirs` whose contents have no
+/// dominant category and that no rule matches
+pub const FOLDERS_CATEGORY: &str = "Folders";
+
+/// The files below a directory organized as a whole
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirContents {
+    /// Total size of the files, without following symlinks
+    pub size: u64,
+    pub files: usize,
+    /// How many of the files fall in each category, when they were classified
+    pub categories: BTreeMap<String, usize>,
+}
+
+impl DirContents {
+    /// Size and number of the files below `dir`
+    pub fn count(dir: &Path) -> Self {
+        let mut contents = Self::default();
+        contents.walk(dir, &mut |_, _| None);
+        contents
+    }
+
+    /// Size and number of the files below `dir`, each also classified the way
+    /// it would be if it were organized on its own
+    pub fn classify(dir: &Path, registry: &CategoryRegistry, detect: DetectMode) -> Self {
+        let mut contents = Self::default();
+        contents.walk(dir, &mut |path, metadata| {
+            let (mime_type, _) = detect_mime(path, detect);
+            let candidate = Candidate::new(path, mime_type.as_deref(), metadata);
+            Some(registry.classify(&candidate, None).0.to_string())
+        });
+        contents
+    }
+
+    fn walk(
+        &mut self,
+        dir: &Path,
+        classify: &mut dyn FnMut(&Path, &fs::Metadata) -> Option<String>,
+    ) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(metadata) if metadata.is_dir() => self.walk(&path, classify),
+                Ok(metadata) => {
+                    self.size += metadata.len();
+                    self.files += 1;
+                    if let Some(category) = classify(&path, &metadata) {
+                        *self.categories.entry(category).or_default() += 1;
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// The category more than half of the files fall in, unless that is the
+    /// `fallback` one, which says nothing about what the directory holds
+    pub fn dominant_category(&self, fallback: &str) -> Option<&str> {
+        self.categories
+            .iter()
+            .find(|(category, count)| **count * 2 > self.files && *category != fallback)
+            .map(|(category, _)| category.as_str())
+    }
+}
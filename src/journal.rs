@@ -0,0 +1,483 @@
+use crate::config::xdg_dir;
+use crate::error::{OrganizerError, Result};
+use crate::transfer::{copy_file, rename_or_copy, TransferMode};
+use crate::trash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How a conflict at the destination was resolved when the file was moved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictAction {
+    None,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum JournalAction {
+    /// A file was renamed from `source` to `destination`
+    Move {
+        source: PathBuf,
+        destination: PathBuf,
+        conflict: ConflictAction,
+        /// Size and mtime of the destination right after the move, used to
+        /// detect files changed since
+        size: u64,
+        modified: Option<u64>,
+    },
+    /// `destination` was created as a copy or link of `source`, which stayed in place
+    CreateFile {
+        source: PathBuf,
+        destination: PathBuf,
+        mode: TransferMode,
+        /// Size and mtime of the destination right after it was created
+        size: u64,
+        modified: Option<u64>,
+    },
+    /// A category directory was created
+    CreateDir { path: PathBuf },
+    /// A directory left empty by the run was removed (`--prune-empty`)
+    RemoveDir { path: PathBuf },
+    /// `path` was removed because it was identical to `duplicate_of`, into the
+    /// trash at `trashed` unless it was deleted for good
+    RemoveDuplicate {
+        path: PathBuf,
+        duplicate_of: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        trashed: Option<PathBuf>,
+    },
+    /// The file at `path` was moved to the trash, at `trashed`, to make way for
+    /// a file replacing it
+    Trash { path: PathBuf, trashed: PathBuf },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub run_id: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub action: JournalAction,
+}
+
+/// Append-only record of every change made during a run
+pub struct Journal {
+    run_id: String,
+    file: Option<File>,
+}
+
+impl Journal {
+    /// Open the journal at `path` for appending, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            run_id: new_run_id(),
+            file: Some(file),
+        })
+    }
+
+    /// A journal that records nothing, used for dry runs
+    pub fn disabled() -> Self {
+        Self {
+            run_id: new_run_id(),
+            file: None,
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn record_move(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        conflict: ConflictAction,
+    ) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let metadata = fs::symlink_metadata(destination)?;
+        self.record(JournalAction::Move {
+            source: absolute(source),
+            destination: absolute(destination),
+            conflict,
+            size: metadata.len(),
+            modified: mtime_millis(&metadata),
+        })
+    }
+
+    pub fn record_create_file(
+        &mut self,
+        source: &Path,
+        destination: &Path,
+        mode: TransferMode,
+    ) -> Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        let metadata = fs::symlink_metadata(destination)?;
+        self.record(JournalAction::CreateFile {
+            source: absolute(source),
+            destination: absolute(destination),
+            mode,
+            size: metadata.len(),
+            modified: mtime_millis(&metadata),
+        })
+    }
+
+    pub fn record_remove_duplicate(
+        &mut self,
+        path: &Path,
+        duplicate_of: &Path,
+        trashed: Option<&Path>,
+    ) -> Result<()> {
+        self.record(JournalAction::RemoveDuplicate {
+            path: absolute(path),
+            duplicate_of: absolute(duplicate_of),
+            trashed: trashed.map(Path::to_path_buf),
+        })
+    }
+
+    pub fn record_trash(&mut self, path: &Path, trashed: &Path) -> Result<()> {
+        self.record(JournalAction::Trash {
+            path: absolute(path),
+            trashed: trashed.to_path_buf(),
+        })
+    }
+
+    pub fn record_create_dir(&mut self, path: &Path) -> Result<()> {
+        self.record(JournalAction::CreateDir {
+            path: absolute(path),
+        })
+    }
+
+    pub fn record_remove_dir(&mut self, path: &Path) -> Result<()> {
+        self.record(JournalAction::RemoveDir {
+            path: absolute(path),
+        })
+    }
+
+    fn record(&mut self, action: JournalAction) -> Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        let entry = JournalEntry {
+            run_id: self.run_id.clone(),
+            timestamp: unix_now(),
+            action,
+        };
+        let line =
+            serde_json::to_string(&entry).map_err(|e| OrganizerError::Journal(e.to_string()))?;
+        // Flush every entry so a crash mid-run still leaves an undoable journal
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// `$XDG_STATE_HOME/organizer/journal.jsonl`, falling back to `~/.local/state/organizer/journal.jsonl`
+pub fn default_journal_path() -> Result<PathBuf> {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+        .map(|dir| dir.join("organizer").join("journal.jsonl"))
+        .ok_or_else(|| OrganizerError::Journal("cannot determine the home directory".to_string()))
+}
+
+pub fn read_entries(path: &Path) -> Result<Vec<JournalEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line).map_err(|e| {
+            OrganizerError::Journal(format!("{}:{}: {}", path.display(), number + 1, e))
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn write_entries(path: &Path, entries: &[JournalEntry]) -> Result<()> {
+    let tmp_path = path.with_extension("jsonl.tmp");
+    let mut file = File::create(&tmp_path)?;
+    for entry in entries {
+        let line =
+            serde_json::to_string(entry).map_err(|e| OrganizerError::Journal(e.to_string()))?;
+        writeln!(file, "{}", line)?;
+    }
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Outcome of undoing a single journal entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UndoStatus {
+    Restored,
+    /// The entry could not be undone and stays in the journal
+    Skipped(String),
+}
+
+#[derive(Debug)]
+pub struct UndoReport {
+    pub run_id: String,
+    pub results: Vec<(JournalAction, UndoStatus)>,
+}
+
+impl UndoReport {
+    /// Number of files moved back to where they came from or out of the trash,
+    /// or copies and links removed
+    pub fn restored(&self) -> usize {
+        self.count_moves(|status| *status == UndoStatus::Restored)
+    }
+
+    /// Number of files left in place
+    pub fn skipped(&self) -> usize {
+        self.count_moves(|status| *status != UndoStatus::Restored)
+    }
+
+    fn count_moves(&self, predicate: impl Fn(&UndoStatus) -> bool) -> usize {
+        self.results
+            .iter()
+            .filter(|(action, status)| {
+                matches!(
+                    action,
+                    JournalAction::Move { .. }
+                        | JournalAction::CreateFile { .. }
+                        | JournalAction::RemoveDuplicate { .. }
+                        | JournalAction::Trash { .. }
+                ) && predicate(status)
+            })
+            .count()
+    }
+}
+
+/// Undo a run (the most recent one when `run_id` is `None`) by replaying its
+/// journal entries in reverse. Entries that were undone are removed from the
+/// journal; entries that could not be undone are kept so they can be retried.
+pub fn undo(journal_path: &Path, run_id: Option<&str>, dry_run: bool) -> Result<UndoReport> {
+    let entries = read_entries(journal_path)?;
+
+    let run_id = match run_id {
+        Some(id) => id.to_string(),
+        None => entries
+            .last()
+            .map(|e| e.run_id.clone())
+            .ok_or_else(|| OrganizerError::Journal("no runs to undo".to_string()))?,
+    };
+
+    let (run_entries, mut remaining): (Vec<_>, Vec<_>) =
+        entries.into_iter().partition(|e| e.run_id == run_id);
+    if run_entries.is_empty() {
+        return Err(OrganizerError::Journal(format!(
+            "run '{}' not found",
+            run_id
+        )));
+    }
+
+    let mut results = Vec::new();
+    let mut kept = Vec::new();
+    let mut vacated = HashSet::new();
+    for entry in run_entries.into_iter().rev() {
+        let status = undo_action(&entry.action, dry_run, &mut vacated);
+        if matches!(status, UndoStatus::Skipped(_)) {
+            kept.push(entry.clone());
+        }
+        results.push((entry.action, status));
+    }
+
+    if !dry_run {
+        kept.reverse();
+        remaining.extend(kept);
+        write_entries(journal_path, &remaining)?;
+    }
+
+    Ok(UndoReport { run_id, results })
+}
+
+/// `vacated` collects the paths moved out so far, letting a dry run tell which
+/// directories would end up empty.
+fn undo_action(
+    action: &JournalAction,
+    dry_run: bool,
+    vacated: &mut HashSet<PathBuf>,
+) -> UndoStatus {
+    match action {
+        JournalAction::Move {
+            source,
+            destination,
+            size,
+            modified,
+            ..
+        } => {
+            let metadata = match fs::symlink_metadata(destination) {
+                Ok(metadata) => metadata,
+                Err(_) => return UndoStatus::Skipped("file was removed since".to_string()),
+            };
+            if metadata.len() != *size || mtime_millis(&metadata) != *modified {
+                return UndoStatus::Skipped("file was modified since".to_string());
+            }
+            if source.exists() {
+                return UndoStatus::Skipped("original location is occupied".to_string());
+            }
+            if !dry_run {
+                let restored = source
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| rename_or_copy(destination, source, &mut |_, _| {}));
+                if let Err(e) = restored {
+                    return UndoStatus::Skipped(e.to_string());
+                }
+            }
+            vacated.insert(destination.clone());
+            UndoStatus::Restored
+        }
+        JournalAction::CreateFile {
+            destination,
+            mode,
+            size,
+            modified,
+            ..
+        } => {
+            // The original never moved, so undoing only removes what was created
+            let metadata = match fs::symlink_metadata(destination) {
+                Ok(metadata) => metadata,
+                Err(_) => return UndoStatus::Skipped("file was removed since".to_string()),
+            };
+            // A hard link shares its contents with the original, so edits to
+            // either don't make removing the link lose anything
+            let unchanged = *mode == TransferMode::Hardlink
+                || (metadata.len() == *size && mtime_millis(&metadata) == *modified);
+            if !unchanged {
+                return UndoStatus::Skipped("file was modified since".to_string());
+            }
+            if !dry_run {
+                if let Err(e) = fs::remove_file(destination) {
+                    return UndoStatus::Skipped(e.to_string());
+                }
+            }
+            vacated.insert(destination.clone());
+            UndoStatus::Restored
+        }
+        JournalAction::RemoveDuplicate {
+            path,
+            trashed: Some(trashed),
+            ..
+        } if trashed.exists() => undo_trash(path, trashed, dry_run, vacated),
+        JournalAction::RemoveDuplicate {
+            path, duplicate_of, ..
+        } => {
+            // The removed file had the same contents, so a verified copy that
+            // keeps the remaining file's permissions and times brings it back
+            if path.exists() {
+                return UndoStatus::Skipped("original location is occupied".to_string());
+            }
+            if !duplicate_of.is_file() {
+                return UndoStatus::Skipped("the remaining copy was removed since".to_string());
+            }
+            if !dry_run {
+                let restored = path
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| copy_file(duplicate_of, path, &mut |_, _| {}));
+                if let Err(e) = restored {
+                    return UndoStatus::Skipped(e.to_string());
+                }
+            }
+            UndoStatus::Restored
+        }
+        JournalAction::CreateDir { path } => {
+            if !path.exists() {
+                return UndoStatus::Restored;
+            }
+            let is_empty = fs::read_dir(path)
+                .map(|mut entries| entries.all(|e| e.is_ok_and(|e| vacated.contains(&e.path()))))
+                .unwrap_or(false);
+            if !is_empty {
+                return UndoStatus::Skipped("directory is not empty".to_string());
+            }
+            if !dry_run {
+                if let Err(e) = fs::remove_dir(path) {
+                    return UndoStatus::Skipped(e.to_string());
+                }
+            }
+            vacated.insert(path.clone());
+            UndoStatus::Restored
+        }
+        JournalAction::RemoveDir { path } => {
+            // Undone before the moves out of it, so they find it in place
+            if path.is_dir() {
+                return UndoStatus::Restored;
+            }
+            if !dry_run {
+                if let Err(e) = fs::create_dir_all(path) {
+                    return UndoStatus::Skipped(e.to_string());
+                }
+            }
+            UndoStatus::Restored
+        }
+        JournalAction::Trash { path, trashed } => undo_trash(path, trashed, dry_run, vacated),
+    }
+}
+
+/// Move a file back out of the trash. Undone after the move that replaced
+/// it, so its original location is free again by then.
+fn undo_trash(
+    path: &Path,
+    trashed: &Path,
+    dry_run: bool,
+    vacated: &HashSet<PathBuf>,
+) -> UndoStatus {
+    if fs::symlink_metadata(path).is_ok() && !vacated.contains(path) {
+        return UndoStatus::Skipped("original location is occupied".to_string());
+    }
+    if fs::symlink_metadata(trashed).is_err() {
+        return UndoStatus::Skipped("no longer in the trash".to_string());
+    }
+    if !dry_run {
+        if let Err(e) = trash::restore(trashed, path) {
+            return UndoStatus::Skipped(e.to_string());
+        }
+    }
+    UndoStatus::Restored
+}
+
+fn new_run_id() -> String {
+    format!("{}-{}", unix_now(), std::process::id())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub(crate) fn mtime_millis(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Journal paths are absolute so `undo` works from any working directory
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,11 +1,50 @@
+pub mod categories;
 pub mod cli;
+pub mod config;
+pub mod conflict;
+pub mod detect;
+pub mod duplicates;
 pub mod error;
+pub mod filter;
+pub mod folders;
+pub mod hash;
+pub mod journal;
+pub mod metadata;
+pub mod plan;
+pub mod projects;
+pub mod rules;
+pub mod scan;
+pub mod tags;
+pub mod template;
+pub mod transfer;
+pub mod trash;
 pub mod tui;
+pub mod watch;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A file placed in `category` with nothing else known about it, for
+    /// tests to fill in the fields they need
+    fn bare_file(path: impl Into<PathBuf>, category: &str) -> cli::FileInfo {
+        cli::FileInfo {
+            path: path.into(),
+            mime_type: None,
+            detected_by: detect::DetectionMethod::Unknown,
+            category: category.to_string(),
+            subdir: PathBuf::new(),
+            rule: None,
+            project: None,
+            contents: None,
+            size: 0,
+            date: None,
+            exif: None,
+            tags: None,
+        }
+    }
 
     #[test]
     fn test_categorize_image_files() {
@@ -67,6 +106,1843 @@ mod tests {
         assert_eq!(cli::categorize_file(&no_mime), "Misc");
     }
 
+    #[test]
+    fn test_registry_from_config() {
+        let config = config::Config::parse(
+            r#"
+            [[category]]
+            name = "Photos"
+            folder = "Pictures"
+            mime = ["image/*"]
+
+            [[category]]
+            name = "Ebooks"
+            extensions = ["epub", ".MOBI"]
+            "#,
+        )
+        .unwrap();
+        let registry = categories::CategoryRegistry::from_config(&config).unwrap();
+
+        let png = Some("image/png");
+        assert_eq!(registry.categorize(Path::new("a.png"), png, None), "Photos");
+        assert_eq!(
+            registry.categorize(Path::new("book.mobi"), None, None),
+            "Ebooks"
+        );
+        assert_eq!(
+            registry.categorize(Path::new("a.pdf"), Some("application/pdf"), None),
+            "Docs"
+        );
+        assert_eq!(registry.categorize(Path::new("a.bin"), None, None), "Misc");
+        assert_eq!(registry.folder("Photos"), "Pictures");
+        assert!(registry.is_category_folder("Pictures"));
+        assert!(!registry.is_category_folder("Photos"));
+        assert_eq!(
+            registry.names(),
+            [
+                "Photos",
+                "Ebooks",
+                "Multimedia",
+                "Docs",
+                "Compressed",
+                "Misc"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_registry_without_builtins() {
+        let config = config::Config::parse(
+            r#"
+            builtin = false
+            fallback = "Other"
+
+            [[category]]
+            name = "Archives"
+            extensions = ["tar.gz", "zip"]
+            "#,
+        )
+        .unwrap();
+        let registry = categories::CategoryRegistry::from_config(&config).unwrap();
+
+        assert_eq!(
+            registry.categorize(Path::new("src.tar.gz"), None, None),
+            "Archives"
+        );
+        assert_eq!(
+            registry.categorize(Path::new("tar.gz"), None, None),
+            "Other"
+        );
+        assert_eq!(
+            registry.categorize(Path::new("a.pdf"), Some("application/pdf"), None),
+            "Other"
+        );
+        assert_eq!(registry.names(), ["Archives", "Other"]);
+    }
+
+    #[test]
+    fn test_registry_rejects_invalid_config() {
+        let nested = config::Config::parse("[[category]]\nname = \"X\"\nfolder = \"a/b\"").unwrap();
+        assert!(categories::CategoryRegistry::from_config(&nested).is_err());
+
+        let duplicate =
+            config::Config::parse("[[category]]\nname = \"X\"\n[[category]]\nname = \"X\"")
+                .unwrap();
+        assert!(categories::CategoryRegistry::from_config(&duplicate).is_err());
+
+        assert!(config::Config::parse("[[category]]\nname = \"X\"\ncolour = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_sniff_magic_bytes() {
+        use detect::sniff_bytes;
+
+        assert_eq!(
+            sniff_bytes(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_bytes(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_bytes(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(
+            sniff_bytes(b"\0\0\0\x20ftypM4A \0\0\0\0"),
+            Some("audio/x-m4a")
+        );
+        assert_eq!(
+            sniff_bytes(b"\0\0\0\x18ftypisom\0\0\0\0"),
+            Some("video/mp4")
+        );
+        assert_eq!(sniff_bytes(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(sniff_bytes(b"\x1f\x8b\x08\0"), Some("application/gzip"));
+        assert_eq!(
+            sniff_bytes(b"PK\x03\x04\x14\0\0\0\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x13\0\0\0[Content_Types].xml....word/document.xml"),
+            Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+        );
+        assert_eq!(sniff_bytes(b"PK\x03\x04notes.txt"), Some("application/zip"));
+        assert_eq!(sniff_bytes(b"hello world"), None);
+        assert_eq!(sniff_bytes(b""), None);
+    }
+
+    #[test]
+    fn test_detect_mime_modes() {
+        use detect::{detect_mime, DetectMode, DetectionMethod};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_detect");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let invoice = temp_dir.join("invoice");
+        fs::write(&invoice, b"%PDF-1.4\n%\xe2\xe3").unwrap();
+        let png = temp_dir.join("image.dat");
+        fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        let notes = temp_dir.join("notes.txt");
+        fs::write(&notes, "plain text").unwrap();
+
+        assert_eq!(
+            detect_mime(&invoice, DetectMode::Both),
+            (
+                Some("application/pdf".to_string()),
+                DetectionMethod::Content
+            )
+        );
+        assert_eq!(
+            detect_mime(&invoice, DetectMode::Extension),
+            (None, DetectionMethod::Unknown)
+        );
+        assert_eq!(
+            detect_mime(&png, DetectMode::Content),
+            (Some("image/png".to_string()), DetectionMethod::Content)
+        );
+        assert_eq!(
+            detect_mime(&notes, DetectMode::Both),
+            (Some("text/plain".to_string()), DetectionMethod::Extension)
+        );
+        assert_eq!(
+            detect_mime(&notes, DetectMode::Content),
+            (None, DetectionMethod::Unknown)
+        );
+
+        // A compound file without a recognizable stream name keeps its extension's type
+        let report = temp_dir.join("report.doc");
+        fs::write(&report, b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1\0\0\0\0").unwrap();
+        assert_eq!(
+            detect_mime(&report, DetectMode::Both),
+            (
+                Some("application/msword".to_string()),
+                DetectionMethod::Extension
+            )
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_journal_undo() {
+        use journal::{ConflictAction, Journal, UndoStatus};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_journal");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let journal_path = temp_dir.join("state").join("journal.jsonl");
+
+        let docs = temp_dir.join("Docs");
+        let (a, b) = (temp_dir.join("a.txt"), temp_dir.join("b.txt"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+
+        let mut journal = Journal::open(&journal_path).unwrap();
+        fs::create_dir(&docs).unwrap();
+        journal.record_create_dir(&docs).unwrap();
+        for source in [&a, &b] {
+            let destination = docs.join(source.file_name().unwrap());
+            fs::rename(source, &destination).unwrap();
+            journal
+                .record_move(source, &destination, ConflictAction::None)
+                .unwrap();
+        }
+
+        // b.txt changed after the run, so it must stay where it is
+        fs::write(docs.join("b.txt"), "changed").unwrap();
+
+        let report = journal::undo(&journal_path, None, false).unwrap();
+        assert_eq!(report.run_id, journal.run_id());
+        assert_eq!(report.restored(), 1);
+        assert_eq!(report.skipped(), 1);
+        assert!(a.exists());
+        assert!(!b.exists());
+        assert!(docs.join("b.txt").exists());
+        assert!(report.results.iter().any(
+            |(_, status)| *status == UndoStatus::Skipped("file was modified since".to_string())
+        ));
+
+        // Only the entries that could not be undone are left in the journal
+        let remaining = journal::read_entries(&journal_path).unwrap();
+        assert_eq!(remaining.len(), 2);
+        assert!(journal::undo(&journal_path, Some("no-such-run"), false).is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_partial_downloads_are_not_organized() {
+        assert!(watch::is_partial_download(Path::new("/tmp/video.mp4.part")));
+        assert!(watch::is_partial_download(Path::new(
+            "setup.exe.CRDOWNLOAD"
+        )));
+        assert!(watch::is_partial_download(Path::new("report.pdf.download")));
+        assert!(!watch::is_partial_download(Path::new("report.pdf")));
+        assert!(!watch::is_partial_download(Path::new("partial.txt")));
+    }
+
+    #[test]
+    fn test_watch_candidates() {
+        use filter::ScanFilter;
+        use projects::ProjectMode;
+
+        let base = std::env::temp_dir().join("organizer_test_watch_candidates");
+        let _ = fs::remove_dir_all(&base);
+        fs::create_dir_all(&base).unwrap();
+        let registry = categories::CategoryRegistry::default();
+        let filter = ScanFilter::new(&[], &[]).unwrap();
+        let candidate = |name: &str| {
+            watch::is_candidate(
+                &base.join(name),
+                &base,
+                &registry,
+                &filter,
+                ProjectMode::Skip,
+            )
+        };
+
+        assert!(candidate("report.pdf"));
+        assert!(candidate("inbox/report.pdf"));
+        assert!(!candidate("Docs/report.pdf"));
+        assert!(!candidate("Duplicates/report.pdf"));
+        assert!(!candidate("report.pdf.part"));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_files_identical() {
+        let temp_dir = std::env::temp_dir().join("organizer_test_identical");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let a = temp_dir.join("a.bin");
+        let b = temp_dir.join("b.bin");
+        let c = temp_dir.join("c.bin");
+        let d = temp_dir.join("d.bin");
+        fs::write(&a, "same contents").unwrap();
+        fs::write(&b, "same contents").unwrap();
+        fs::write(&c, "same length!!").unwrap();
+        fs::write(&d, "shorter").unwrap();
+
+        assert!(hash::files_identical(&a, &b).unwrap());
+        assert!(!hash::files_identical(&a, &c).unwrap());
+        assert!(!hash::files_identical(&a, &d).unwrap());
+        assert_eq!(hash::hash_file(&a).unwrap(), hash::hash_file(&b).unwrap());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        use crate::duplicates::{find_duplicates, KeepPolicy};
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_duplicates");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("nested")).unwrap();
+
+        let registry = categories::CategoryRegistry::default();
+        let write = |name: &str, contents: &str, age_secs: u64| {
+            let path = temp_dir.join(name);
+            fs::write(&path, contents).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+                .unwrap();
+            cli::FileInfo {
+                size: contents.len() as u64,
+                ..bare_file(path, registry.fallback())
+            }
+        };
+        let files = vec![
+            write("a.txt", "same contents", 30),
+            write("nested/copy of a.txt", "same contents", 10),
+            write("b.txt", "same length!!", 20),
+            write("c.txt", "unique", 0),
+            write("empty1.txt", "", 0),
+            write("empty2.txt", "", 0),
+        ];
+
+        let groups = find_duplicates(&files);
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+        assert_eq!(group.files.len(), 2);
+        assert_eq!(group.wasted(), 13);
+        assert_eq!(group.keeper(KeepPolicy::Oldest), temp_dir.join("a.txt"));
+        assert_eq!(
+            group.keeper(KeepPolicy::Newest),
+            temp_dir.join("nested/copy of a.txt")
+        );
+        assert_eq!(
+            group.keeper(KeepPolicy::ShortestPath),
+            temp_dir.join("a.txt")
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_move_duplicates() {
+        use crate::duplicates::{find_duplicates, move_extras, KeepPolicy};
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_move_duplicates");
+        let _ = fs::remove_dir_all(&temp_dir);
+        for dir in ["Docs", "sub"] {
+            fs::create_dir_all(temp_dir.join(dir)).unwrap();
+        }
+        for name in ["report.pdf", "Docs/report.pdf", "sub/report.pdf"] {
+            fs::write(temp_dir.join(name), "%PDF-1.4 same").unwrap();
+        }
+
+        // The copy already organized into Docs is found along with the others
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from([
+            "organizer".as_ref(),
+            "-r".as_ref(),
+            temp_dir.as_os_str(),
+        ]);
+        let files = scan::scan_with_category_folders(&temp_dir, &registry, &args)
+            .unwrap()
+            .files;
+        let groups = find_duplicates(&files);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 3);
+
+        // Extra copies of the same name get their own names, in a dry run too
+        let mut journal = journal::Journal::disabled();
+        for dry_run in [true, false] {
+            let moved: Vec<_> = move_extras(
+                &groups,
+                KeepPolicy::ShortestPath,
+                &temp_dir,
+                &mut journal,
+                dry_run,
+            )
+            .into_iter()
+            .map(|(_, destination)| destination.unwrap())
+            .collect();
+            assert_eq!(
+                moved,
+                [
+                    temp_dir.join("Duplicates/report.pdf"),
+                    temp_dir.join("Duplicates/report_1.pdf")
+                ]
+            );
+        }
+        assert!(temp_dir.join("report.pdf").exists());
+        assert!(!temp_dir.join("Docs/report.pdf").exists());
+        assert!(temp_dir.join("Duplicates/report_1.pdf").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_template_render() {
+        use crate::template::Template;
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 7)
+            .unwrap()
+            .and_hms_opt(12, 0, 0);
+        let file = |name: &str, date| cli::FileInfo {
+            date,
+            ..bare_file(Path::new("/downloads").join(name), "Docs")
+        };
+
+        let template = Template::parse("{category}/{year}/{month}/{name}.{ext}").unwrap();
+        assert_eq!(
+            template.render(&file("invoice.pdf", date), "Documents"),
+            PathBuf::from("Documents/2024/03/invoice.pdf")
+        );
+        assert_eq!(
+            template.render(&file("README", date), "Documents"),
+            PathBuf::from("Documents/2024/03/README")
+        );
+        assert_eq!(
+            template.render(&file("notes.txt", None), "Docs"),
+            PathBuf::from("Docs/Unknown/Unknown/notes.txt")
+        );
+
+        assert!(Template::parse("{category}/{week}/{name}").is_err());
+        assert!(Template::parse("{category/{name}").is_err());
+        assert!(Template::parse("../{name}").is_err());
+        assert!(Template::parse("/tmp/{name}").is_err());
+        assert!(Template::parse("{category}/").is_err());
+    }
+
+    #[test]
+    fn test_exif_categories() {
+        use crate::metadata::ExifInfo;
+        use crate::template::Template;
+
+        let config = config::Config::parse(
+            r#"
+            [[category]]
+            name = "Photos"
+            mime = ["image/*"]
+            camera = true
+            template = "Photos/{exif.year}/{exif.camera}/{filename}"
+
+            [[category]]
+            name = "Screenshots"
+            mime = ["image/png"]
+            camera = false
+            "#,
+        )
+        .unwrap();
+        let registry = categories::CategoryRegistry::from_config(&config).unwrap();
+
+        let photo = ExifInfo {
+            captured: chrono::NaiveDate::from_ymd_opt(2016, 5, 4)
+                .unwrap()
+                .and_hms_opt(3, 2, 1),
+            camera: Some("Canon EOS 5D".to_string()),
+            has_gps: true,
+        };
+        let png = Some("image/png");
+        let jpeg = Some("image/jpeg");
+        assert_eq!(
+            registry.categorize(Path::new("a.jpg"), jpeg, Some(&photo)),
+            "Photos"
+        );
+        assert_eq!(
+            registry.categorize(Path::new("a.png"), png, None),
+            "Screenshots"
+        );
+        let no_camera = ExifInfo::default();
+        assert_eq!(
+            registry.categorize(Path::new("a.png"), png, Some(&no_camera)),
+            "Screenshots"
+        );
+        // Images that are neither fall through to the built-in categories
+        assert_eq!(
+            registry.categorize(Path::new("a.jpg"), jpeg, None),
+            "Multimedia"
+        );
+
+        let file = cli::FileInfo {
+            mime_type: jpeg.map(String::from),
+            detected_by: detect::DetectionMethod::Content,
+            exif: Some(photo),
+            ..bare_file("IMG_0001.JPG", "Photos")
+        };
+        let template = registry.template("Photos").unwrap();
+        assert_eq!(
+            template.render(&file, "Photos"),
+            PathBuf::from("Photos/2016/Canon EOS 5D/IMG_0001.JPG")
+        );
+        let file = cli::FileInfo { exif: None, ..file };
+        assert_eq!(
+            Template::parse("{exif.camera}/{filename}")
+                .unwrap()
+                .render(&file, "Photos"),
+            PathBuf::from("Unknown/IMG_0001.JPG")
+        );
+    }
+
+    #[test]
+    fn test_audio_tags() {
+        use crate::tags::read_tags;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_tags");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // ID3v2.3 with a Latin-1 artist, a UTF-16 title and a "track/total" number
+        let frame = |id: &[u8], body: &[u8]| {
+            let mut frame = id.to_vec();
+            frame.extend((body.len() as u32).to_be_bytes());
+            frame.extend([0, 0]);
+            frame.extend(body);
+            frame
+        };
+        let title: Vec<u8> = [1, 0xff, 0xfe]
+            .into_iter()
+            .chain("Caf\u{e9}".encode_utf16().flat_map(|c| c.to_le_bytes()))
+            .collect();
+        let mut frames = frame(b"TPE1", b"\0Daft Punk");
+        frames.extend(frame(b"TALB", b"\0Discovery"));
+        frames.extend(frame(b"TIT2", &title));
+        frames.extend(frame(b"TRCK", b"\x003/14"));
+        frames.extend(frame(b"APIC", &[0; 64]));
+        let size = frames.len() as u32;
+        let mut mp3 = b"ID3\x03\0\0".to_vec();
+        mp3.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7f) as u8));
+        mp3.extend(frames);
+        mp3.extend([0xff, 0xfb, 0x90, 0x00]);
+        let path = temp_dir.join("song.mp3");
+        fs::write(&path, &mp3).unwrap();
+
+        let tags = read_tags(&path, Some("audio/mpeg")).unwrap();
+        assert_eq!(tags.artist.as_deref(), Some("Daft Punk"));
+        assert_eq!(tags.album.as_deref(), Some("Discovery"));
+        assert_eq!(tags.title.as_deref(), Some("Caf\u{e9}"));
+        assert_eq!(tags.track, Some(3));
+
+        // A file cut off inside a frame keeps the frames before it
+        let cut = mp3.windows(4).position(|w| w == b"3/14").unwrap() + 2;
+        fs::write(&path, &mp3[..cut]).unwrap();
+        let tags = read_tags(&path, Some("audio/mpeg")).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("Caf\u{e9}"));
+        assert_eq!(tags.track, None);
+
+        // FLAC with a Vorbis comment block
+        let comments = [
+            "ARTIST=Air",
+            "album=Moon Safari",
+            "TITLE=La femme d'argent",
+            "TRACKNUMBER=1",
+        ];
+        let mut block = 6u32.to_le_bytes().to_vec();
+        block.extend(b"vendor");
+        block.extend((comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend((comment.len() as u32).to_le_bytes());
+            block.extend(comment.as_bytes());
+        }
+        let mut flac = b"fLaC".to_vec();
+        flac.extend([0x00, 0, 0, 34]);
+        flac.extend([0; 34]);
+        flac.push(0x84);
+        flac.extend(&(block.len() as u32).to_be_bytes()[1..]);
+        flac.extend(block);
+        let path = temp_dir.join("song.flac");
+        fs::write(&path, &flac).unwrap();
+
+        let tags = read_tags(&path, Some("audio/flac")).unwrap();
+        assert_eq!(tags.artist.as_deref(), Some("Air"));
+        assert_eq!(tags.album.as_deref(), Some("Moon Safari"));
+        assert_eq!(tags.title.as_deref(), Some("La femme d'argent"));
+        assert_eq!(tags.track, Some(1));
+        fs::write(&path, &flac[..flac.len() - 5]).unwrap();
+        let tags = read_tags(&path, Some("audio/flac")).unwrap();
+        assert_eq!(tags.title.as_deref(), Some("La femme d'argent"));
+        assert_eq!(tags.track, None);
+
+        assert_eq!(read_tags(&path, Some("text/plain")), None);
+        fs::write(&path, b"not audio").unwrap();
+        assert_eq!(read_tags(&path, Some("audio/flac")), None);
+
+        // An atom claiming a 64-bit size near u64::MAX is cut off at the end of the file
+        let mut m4a = b"\0\0\0\x10ftypM4A \0\0\0\0\0\0\0\x01moov".to_vec();
+        m4a.extend((u64::MAX - 8).to_be_bytes());
+        let path = temp_dir.join("crafted.m4a");
+        fs::write(&path, &m4a).unwrap();
+        assert_eq!(read_tags(&path, Some("audio/x-m4a")), None);
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_music_template() {
+        use crate::tags::AudioTags;
+        use crate::template::sanitize;
+
+        let mut registry = categories::CategoryRegistry::default();
+        registry.enable_music();
+        assert_eq!(
+            registry.categorize(Path::new("a.mp3"), Some("audio/mpeg"), None),
+            "Music"
+        );
+
+        let file = |tags| cli::FileInfo {
+            mime_type: Some("audio/mpeg".to_string()),
+            detected_by: detect::DetectionMethod::Content,
+            tags,
+            ..bare_file("01 track.mp3", "Music")
+        };
+        let template = registry.template("Music").unwrap();
+        let tagged = AudioTags {
+            artist: Some("AC/DC".to_string()),
+            album: Some("Who Made Who?".to_string()),
+            title: Some("Hells Bells".to_string()),
+            track: Some(7),
+            ..AudioTags::default()
+        };
+        assert_eq!(
+            template.render(&file(Some(tagged)), "Music"),
+            PathBuf::from("Music/AC_DC/Who Made Who_/07 - Hells Bells.mp3")
+        );
+        assert_eq!(
+            template.render(&file(None), "Music"),
+            PathBuf::from("Music/Unknown/Unknown/00 - 01 track.mp3")
+        );
+
+        assert_eq!(sanitize("a:b*c?"), "a_b_c_");
+        assert_eq!(sanitize(" Greatest Hits... "), "Greatest Hits");
+        assert_eq!(sanitize("con"), "con_");
+        assert_eq!(sanitize(".."), "_");
+        assert!(sanitize(&"x".repeat(300)).len() <= 200);
+        assert!(crate::template::Template::parse("{track:x2}").is_err());
+    }
+
+    #[test]
+    fn test_pdf_creation_date() {
+        let date = |s: &[u8]| metadata::parse_pdf_date(s).map(|d| d.to_string());
+
+        assert_eq!(
+            date(b"<< /Producer (x) /CreationDate (D:20230415093000+02'00') >>").as_deref(),
+            Some("2023-04-15 09:30:00")
+        );
+        assert_eq!(
+            date(b"/CreationDate(D:2021)").as_deref(),
+            Some("2021-01-01 00:00:00")
+        );
+        assert_eq!(date(b"/CreationDate (D:20231399)"), None);
+        assert_eq!(date(b"/ModDate (D:20230415)"), None);
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        use crate::plan::{apply_action, check_stale, Plan, Resolution};
+        use clap::Parser;
+        use std::collections::HashSet;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_plan");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("sub")).unwrap();
+        fs::write(temp_dir.join("notes.txt"), "top").unwrap();
+        fs::write(temp_dir.join("sub/notes.txt"), "nested").unwrap();
+        fs::write(temp_dir.join("report.pdf"), "%PDF-1.4").unwrap();
+
+        let args = cli::MyOrganizer::parse_from([
+            "organizer".as_ref(),
+            "-r".as_ref(),
+            temp_dir.as_os_str(),
+        ]);
+        let registry = categories::CategoryRegistry::default();
+        let mut files: Vec<cli::FileInfo> = ["notes.txt", "sub/notes.txt", "report.pdf"]
+            .iter()
+            .map(|name| {
+                let path = temp_dir.join(name);
+                let metadata = fs::metadata(&path).unwrap();
+                cli::file_info(path, &metadata, &registry, &args)
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mode = transfer::TransferMode::Move;
+        let plan = Plan::build(&files, &temp_dir, &temp_dir, &registry, "skip", mode).unwrap();
+        let json = serde_json::to_string(&plan).unwrap();
+        assert_eq!(serde_json::from_str::<Plan>(&json).unwrap(), plan);
+
+        // Both notes want Docs/notes.txt; the later one in the plan is skipped
+        let resolutions: Vec<_> = plan.actions.iter().map(|a| a.resolution).collect();
+        assert_eq!(
+            resolutions,
+            [Resolution::Move, Resolution::Move, Resolution::Skip]
+        );
+        assert_eq!(plan.actions[0].destination, temp_dir.join("Docs/notes.txt"));
+        assert_eq!(plan.actions[2].source, temp_dir.join("sub/notes.txt"));
+
+        // Applying refuses the action whose source changed since planning
+        fs::write(temp_dir.join("report.pdf"), "%PDF-1.7 edited").unwrap();
+        assert!(check_stale(&plan.actions[1]).is_some());
+        assert_eq!(check_stale(&plan.actions[0]), None);
+
+        let mut journal = journal::Journal::disabled();
+        apply_action(
+            &plan.actions[0],
+            mode,
+            &mut journal,
+            None,
+            false,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        assert!(temp_dir.join("Docs/notes.txt").exists());
+        assert!(temp_dir.join("sub/notes.txt").exists());
+        assert!(temp_dir.join("report.pdf").exists());
+
+        // An overwrite is refused once the file it would replace has changed
+        let overwrite = plan::plan_action(
+            &files[2],
+            &temp_dir,
+            &registry,
+            "overwrite",
+            mode,
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(overwrite.resolution, Resolution::Overwrite);
+        assert_eq!(check_stale(&overwrite), None);
+        fs::write(temp_dir.join("Docs/notes.txt"), "edited after planning").unwrap();
+        assert_eq!(
+            check_stale(&overwrite).as_deref(),
+            Some("destination changed since planning")
+        );
+
+        // A dangling symlink takes up its name like any other file
+        #[cfg(unix)]
+        {
+            let action = plan::plan_action(
+                &files[1],
+                &temp_dir,
+                &registry,
+                "skip",
+                mode,
+                &HashSet::new(),
+            )
+            .unwrap();
+            assert_eq!(action.resolution, Resolution::Move);
+            std::os::unix::fs::symlink(temp_dir.join("missing"), &action.destination).unwrap();
+            assert_eq!(
+                check_stale(&action).as_deref(),
+                Some("destination appeared since planning")
+            );
+            let renamed = plan::plan_action(
+                &files[1],
+                &temp_dir,
+                &registry,
+                "dedupe",
+                mode,
+                &HashSet::new(),
+            )
+            .unwrap();
+            assert_eq!(renamed.destination, temp_dir.join("Docs/report_1.pdf"));
+        }
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_trash() {
+        use crate::plan::{apply_action, plan_action};
+        use clap::Parser;
+        use std::collections::HashSet;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_trash");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("Docs")).unwrap();
+        let trash = trash::Trash::at(temp_dir.join("Trash"));
+
+        // Files of the same name get their own entries, each with an info file
+        let draft = temp_dir.join("old draft.txt");
+        fs::write(&draft, "first").unwrap();
+        let first = trash.put(&draft).unwrap();
+        fs::write(&draft, "second").unwrap();
+        let second = trash.put(&draft).unwrap();
+        assert_eq!(first, temp_dir.join("Trash/files/old draft.txt"));
+        assert_eq!(second, temp_dir.join("Trash/files/old draft_1.txt"));
+        let info =
+            fs::read_to_string(temp_dir.join("Trash/info/old draft_1.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("/old%20draft.txt\nDeletionDate="));
+        trash::restore(&second, &draft).unwrap();
+        assert_eq!(fs::read_to_string(&draft).unwrap(), "second");
+        assert!(!temp_dir
+            .join("Trash/info/old draft_1.txt.trashinfo")
+            .exists());
+
+        // Names keep counting past the limit on renaming conflicts
+        for attempt in 1..=1000 {
+            fs::write(
+                temp_dir.join(format!("Trash/info/old draft_{}.txt.trashinfo", attempt)),
+                "",
+            )
+            .unwrap();
+        }
+        fs::write(&draft, "third").unwrap();
+        assert_eq!(
+            trash.put(&draft).unwrap(),
+            temp_dir.join("Trash/files/old draft_1001.txt")
+        );
+
+        // Overwriting sends the replaced file to the trash, and undo brings it back
+        let notes = temp_dir.join("notes.txt");
+        fs::write(&notes, "new").unwrap();
+        fs::write(temp_dir.join("Docs/notes.txt"), "old").unwrap();
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from(["organizer".as_ref(), temp_dir.as_os_str()]);
+        let info = cli::file_info(
+            notes.clone(),
+            &fs::metadata(&notes).unwrap(),
+            &registry,
+            &args,
+        );
+        let mode = transfer::TransferMode::Move;
+        let action = plan_action(
+            &info,
+            &temp_dir,
+            &registry,
+            "overwrite",
+            mode,
+            &HashSet::new(),
+        )
+        .unwrap();
+        let journal_path = temp_dir.join("state/journal.jsonl");
+        let mut journal = journal::Journal::open(&journal_path).unwrap();
+        apply_action(
+            &action,
+            mode,
+            &mut journal,
+            Some(&trash),
+            false,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("Docs/notes.txt")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("Trash/files/notes.txt")).unwrap(),
+            "old"
+        );
+
+        assert_eq!(
+            journal::undo(&journal_path, None, false)
+                .unwrap()
+                .restored(),
+            2
+        );
+        assert_eq!(fs::read_to_string(&notes).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("Docs/notes.txt")).unwrap(),
+            "old"
+        );
+        assert!(!temp_dir.join("Trash/files/notes.txt").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_conflict_strategies() {
+        use crate::conflict::{Asker, Choice};
+        use crate::plan::{plan_action, Resolution};
+        use clap::Parser;
+        use std::collections::HashSet;
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_strategies");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("Docs")).unwrap();
+        let notes = temp_dir.join("notes.txt");
+        let existing = temp_dir.join("Docs/notes.txt");
+        fs::write(&notes, "a longer draft").unwrap();
+        fs::write(&existing, "short").unwrap();
+        // The incoming file is larger but a day older than the one in place
+        let yesterday = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&notes)
+            .unwrap()
+            .set_modified(yesterday)
+            .unwrap();
+
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from(["organizer".as_ref(), temp_dir.as_os_str()]);
+        let info = cli::file_info(
+            notes.clone(),
+            &fs::metadata(&notes).unwrap(),
+            &registry,
+            &args,
+        );
+        let resolve = |conflict: &str| {
+            let mode = transfer::TransferMode::Move;
+            plan_action(&info, &temp_dir, &registry, conflict, mode, &HashSet::new()).unwrap()
+        };
+        assert_eq!(resolve("larger").resolution, Resolution::Overwrite);
+        assert!(resolve("larger")
+            .reason
+            .ends_with("larger than the existing file, replaces it"));
+        assert_eq!(resolve("newer").resolution, Resolution::Skip);
+        assert!(resolve("newer")
+            .reason
+            .ends_with("existing file is at least as new, skipped"));
+
+        assert_eq!(
+            Choice::from_key('r'),
+            Some(Choice {
+                strategy: "rename",
+                apply_to_all: false
+            })
+        );
+        assert_eq!(
+            Choice::from_key('L'),
+            Some(Choice {
+                strategy: "larger",
+                apply_to_all: true
+            })
+        );
+        assert_eq!(Choice::from_key('x'), None);
+
+        // An answer for all conflicts is remembered, and only taken destinations ask
+        let mut asker = Asker::default();
+        let mut asked = 0;
+        let mut ask = |key| {
+            asker
+                .strategy("ask", &notes, &existing, |clash| {
+                    asked += 1;
+                    assert_eq!(clash.destination.size, 5);
+                    assert!(!clash.identical());
+                    Ok(Choice::from_key(key).unwrap())
+                })
+                .unwrap()
+        };
+        assert_eq!(ask('o'), "overwrite");
+        assert_eq!(ask('S'), "skip");
+        assert_eq!(ask('r'), "skip");
+        assert_eq!(asked, 2);
+        let free = temp_dir.join("Docs/other.txt");
+        assert_eq!(
+            Asker::default()
+                .strategy("ask", &notes, &free, |_| unreachable!())
+                .unwrap(),
+            "ask"
+        );
+        assert_eq!(
+            Asker::default()
+                .strategy("newer", &notes, &existing, |_| unreachable!())
+                .unwrap(),
+            "newer"
+        );
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_dedupe_move_file() {
+        use clap::Parser;
+        use cli::MoveOutcome;
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_dedupe");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("Docs")).unwrap();
+        let notes = temp_dir.join("notes.txt");
+        let existing = temp_dir.join("Docs/notes.txt");
+        fs::write(&notes, "same").unwrap();
+        fs::write(&existing, "same").unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let file = fs::File::options().write(true).open(&existing).unwrap();
+        file.set_modified(modified).unwrap();
+        let mut permissions = file.metadata().unwrap().permissions();
+        permissions.set_readonly(true);
+        file.set_permissions(permissions).unwrap();
+        drop(file);
+
+        let registry = categories::CategoryRegistry::default();
+        let parse = |extra: &[&str]| {
+            let mut argv = vec![
+                "organizer",
+                temp_dir.to_str().unwrap(),
+                "-c",
+                "dedupe",
+                "--no-trash",
+            ];
+            argv.extend(extra);
+            cli::MyOrganizer::parse_from(argv)
+        };
+        let info = cli::file_info(
+            notes.clone(),
+            &fs::metadata(&notes).unwrap(),
+            &registry,
+            &parse(&[]),
+        );
+        let journal_path = temp_dir.join("state/journal.jsonl");
+        let mut journal = journal::Journal::open(&journal_path).unwrap();
+        let mut organize = |args: &cli::MyOrganizer| {
+            cli::move_file(
+                &info,
+                &temp_dir,
+                &registry,
+                &mut journal,
+                args,
+                &args.conflict,
+                &mut |_, _| {},
+            )
+            .unwrap()
+        };
+
+        // A dry run reports the duplicate but leaves it in place
+        assert_eq!(
+            organize(&parse(&["--dry-run"])),
+            MoveOutcome::Duplicate(existing.clone())
+        );
+        assert!(notes.exists());
+
+        assert_eq!(
+            organize(&parse(&[])),
+            MoveOutcome::Duplicate(existing.clone())
+        );
+        assert!(!notes.exists());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "same");
+
+        // Without a trash, undo copies the remaining file back with its times and permissions
+        assert_eq!(
+            journal::undo(&journal_path, None, false)
+                .unwrap()
+                .restored(),
+            1
+        );
+        assert_eq!(fs::read_to_string(&notes).unwrap(), "same");
+        let metadata = fs::metadata(&notes).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert!(metadata.permissions().readonly());
+        assert!(existing.exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_copy_and_remove() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_copy");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Stands in for a move to another mount, which a rename can't do
+        let source = temp_dir.join("video.mp4");
+        let contents = vec![7u8; 3 * 1024 * 1024 + 5];
+        fs::write(&source, &contents).unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let file = fs::File::options().write(true).open(&source).unwrap();
+        file.set_modified(modified).unwrap();
+        let mut permissions = file.metadata().unwrap().permissions();
+        permissions.set_readonly(true);
+        file.set_permissions(permissions).unwrap();
+        drop(file);
+
+        let destination = temp_dir.join("video_copy.mp4");
+        let mut reports = Vec::new();
+        transfer::copy_and_remove(&source, &destination, &mut |copied, total| {
+            reports.push((copied, total))
+        })
+        .unwrap();
+
+        assert!(!source.exists());
+        assert_eq!(fs::read(&destination).unwrap(), contents);
+        let metadata = fs::metadata(&destination).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert!(metadata.permissions().readonly());
+        assert_eq!(reports.len(), 4);
+        assert_eq!(
+            reports.last(),
+            Some(&(contents.len() as u64, contents.len() as u64))
+        );
+        assert!(!temp_dir.join(".video_copy.mp4.organizer-part").exists());
+
+        // A copy that can't be written leaves the source alone
+        fs::write(&source, "keep me").unwrap();
+        let missing = temp_dir.join("no/such/dir/video.mp4");
+        assert!(transfer::copy_and_remove(&source, &missing, &mut |_, _| {}).is_err());
+        assert_eq!(fs::read_to_string(&source).unwrap(), "keep me");
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_filters() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_filters");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("keep")).unwrap();
+        fs::create_dir_all(temp_dir.join("sub/logs")).unwrap();
+        for name in [
+            "a.txt",
+            "disk.iso",
+            "keep/b.txt",
+            "sub/c.txt",
+            "sub/d.log",
+            "sub/logs/e.log",
+        ] {
+            fs::write(temp_dir.join(name), name).unwrap();
+        }
+        fs::write(temp_dir.join(filter::IGNORE_FILE), "keep/\n").unwrap();
+        fs::write(
+            temp_dir.join("sub").join(filter::IGNORE_FILE),
+            "*.log\n!logs/e.log\n",
+        )
+        .unwrap();
+
+        let registry = categories::CategoryRegistry::default();
+        let scan = |extra: &[&str]| {
+            let mut argv = vec!["organizer", "-r", temp_dir.to_str().unwrap()];
+            argv.extend(extra);
+            let args = cli::MyOrganizer::parse_from(argv);
+            let mut names: Vec<String> = scan::scan_directory(&temp_dir, &registry, &args)?
+                .files
+                .iter()
+                .map(|f| {
+                    f.path
+                        .strip_prefix(&temp_dir)
+                        .unwrap()
+                        .display()
+                        .to_string()
+                })
+                .collect();
+            names.sort();
+            Ok::<_, error::OrganizerError>(names)
+        };
+
+        // Ignore files apply at their own level and below, and deeper ones can re-include
+        assert_eq!(
+            scan(&["--exclude", "*.iso"]).unwrap(),
+            ["a.txt", "sub/c.txt", "sub/logs/e.log"]
+        );
+        assert_eq!(
+            scan(&["--include", "sub/**", "--exclude", "sub/logs/"]).unwrap(),
+            ["sub/c.txt"]
+        );
+        assert!(matches!(
+            scan(&["--include", "a[.txt"]),
+            Err(error::OrganizerError::Pattern(_))
+        ));
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_size_and_age_filters() {
+        use clap::Parser;
+        use filter::{parse_age, parse_size};
+        use std::time::{Duration, SystemTime};
+
+        assert_eq!(parse_size("500MB"), Ok(500 * 1024 * 1024));
+        assert_eq!(parse_size("1.5k"), Ok(1536));
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("5 parsecs").is_err());
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_age("90min"), Ok(Duration::from_secs(5400)));
+        assert!(parse_age("7").is_err());
+        // Values too big to represent are rejected, not clamped
+        assert!(parse_size("16777216TB").is_err());
+        assert!(parse_size(&"9".repeat(400)).is_err());
+        assert!(parse_age("99999999999999999999999y").is_err());
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_limits");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let write = |name: &str, size: usize, age_days: u64| {
+            let path = temp_dir.join(name);
+            fs::write(&path, vec![b'x'; size]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age_days * 86400);
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("old_big.bin", 4096, 30);
+        write("old_small.bin", 10, 30);
+        write("new_big.bin", 4096, 0);
+
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from([
+            "organizer",
+            temp_dir.to_str().unwrap(),
+            "--older-than",
+            "1w",
+            "--min-size",
+            "1KB",
+        ]);
+        let scan = scan::scan_directory(&temp_dir, &registry, &args).unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert!(scan.files[0].path.ends_with("old_big.bin"));
+        assert_eq!((scan.filtered.size, scan.filtered.age), (1, 1));
+        assert_eq!(scan.filtered.describe(), "1 by size, 1 by age");
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_hidden_and_depth() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_hidden");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("a/b/c")).unwrap();
+        fs::create_dir_all(temp_dir.join(".cache")).unwrap();
+        for file in [
+            "top.txt",
+            ".env",
+            "a/one.txt",
+            "a/b/two.txt",
+            "a/b/c/three.txt",
+            ".cache/config",
+        ] {
+            fs::write(temp_dir.join(file), "x").unwrap();
+        }
+
+        let registry = categories::CategoryRegistry::default();
+        let scan = |extra: &[&str]| {
+            let mut argv = vec!["organizer", temp_dir.to_str().unwrap(), "-r"];
+            argv.extend_from_slice(extra);
+            let args = cli::MyOrganizer::parse_from(argv);
+            let scan = scan::scan_directory(&temp_dir, &registry, &args).unwrap();
+            let mut names: Vec<_> = scan
+                .files
+                .iter()
+                .map(|f| f.path.strip_prefix(&temp_dir).unwrap().to_path_buf())
+                .collect();
+            names.sort();
+            (names, scan.filtered.hidden)
+        };
+        let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        // Hidden files are skipped by default and hidden directories aren't scanned
+        assert_eq!(
+            scan(&["--max-depth", "1"]),
+            (paths(&["a/one.txt", "top.txt"]), 1)
+        );
+        assert_eq!(
+            scan(&["--hidden", "only"]),
+            (paths(&[".cache/config", ".env"]), 4)
+        );
+        assert_eq!(
+            scan(&["--hidden", "include", "--max-depth", "0"]).0,
+            paths(&[".env", "top.txt"])
+        );
+
+        // --max-depth only makes sense for a recursive scan
+        assert!(cli::MyOrganizer::try_parse_from(["organizer", ".", "--max-depth", "1"]).is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() {
+        use clap::Parser;
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_symlinks");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let base = temp_dir.join("base");
+        fs::create_dir_all(base.join("sub")).unwrap();
+        fs::create_dir_all(temp_dir.join("elsewhere")).unwrap();
+        fs::write(base.join("real.txt"), "real").unwrap();
+        fs::write(temp_dir.join("elsewhere/far.txt"), "far").unwrap();
+        symlink("real.txt", base.join("link.txt")).unwrap();
+        symlink("..", base.join("sub/loop")).unwrap();
+        symlink("../elsewhere", base.join("linked")).unwrap();
+        symlink("../elsewhere/far.txt", base.join("far-link.txt")).unwrap();
+
+        let registry = categories::CategoryRegistry::default();
+        let args = |policy: &str| {
+            cli::MyOrganizer::parse_from([
+                "organizer",
+                base.to_str().unwrap(),
+                "-r",
+                "--follow-symlinks",
+                policy,
+            ])
+        };
+        let scan = |args: &cli::MyOrganizer| {
+            let mut names: Vec<_> = scan::scan_directory(&base, &registry, args)
+                .unwrap()
+                .files
+                .into_iter()
+                .map(|f| f.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Loops are entered once and a file reached twice is organized once.
+        // Links leading outside the organized directory are not followed, so
+        // the link to far.txt moves as a link and the linked directory is skipped.
+        assert_eq!(scan(&args("all")), ["far-link.txt", "real.txt"]);
+        assert_eq!(scan(&args("files")), ["far-link.txt", "real.txt"]);
+
+        // By default the link itself moves, and still resolves afterwards
+        let args = args("never");
+        assert_eq!(scan(&args), ["far-link.txt", "link.txt", "real.txt"]);
+        let link = base.join("link.txt");
+        let info = cli::file_info(
+            link.clone(),
+            &fs::metadata(&link).unwrap(),
+            &registry,
+            &args,
+        );
+        let mut journal = journal::Journal::disabled();
+        cli::move_file(
+            &info,
+            &base,
+            &registry,
+            &mut journal,
+            &args,
+            &args.conflict,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let moved = base.join("Docs/link.txt");
+        assert!(moved.is_symlink());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "real");
+        assert!(base.join("real.txt").is_file());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_empty_dirs() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_prune");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let base = temp_dir.join("base");
+        for dir in ["a/b", "kept", "untouched", "Docs"] {
+            fs::create_dir_all(base.join(dir)).unwrap();
+        }
+        for file in ["a/b/x.txt", "kept/y.txt", "kept/.hidden", "Docs/z.txt"] {
+            fs::write(base.join(file), file).unwrap();
+        }
+
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from([
+            "organizer",
+            base.to_str().unwrap(),
+            "-r",
+            "--prune-empty",
+        ]);
+        let journal_path = temp_dir.join("journal.jsonl");
+        let mut journal = journal::Journal::open(&journal_path).unwrap();
+        let mut vacated = Vec::new();
+        for file in scan::scan_directory(&base, &registry, &args).unwrap().files {
+            cli::move_file(
+                &file,
+                &base,
+                &registry,
+                &mut journal,
+                &args,
+                &args.conflict,
+                &mut |_, _| {},
+            )
+            .unwrap();
+            vacated.push(file.path);
+        }
+
+        // A dry run reports the same directories without removing them
+        let mut disabled = journal::Journal::disabled();
+        let would_prune =
+            scan::prune_empty_dirs(&base, &vacated, &registry, &mut disabled, true).unwrap();
+        let pruned =
+            scan::prune_empty_dirs(&base, &vacated, &registry, &mut journal, false).unwrap();
+        assert_eq!(pruned, [base.join("a/b"), base.join("a")]);
+        assert_eq!(would_prune, pruned);
+        assert!(!base.join("a").exists());
+        // Still holds a hidden file, was empty before the run, or is a category folder
+        assert!(base.join("kept").is_dir());
+        assert!(base.join("untouched").is_dir());
+        assert!(base.join("Docs").is_dir());
+
+        let report = journal::undo(&journal_path, None, false).unwrap();
+        assert_eq!(report.skipped(), 0);
+        assert!(base.join("a/b/x.txt").is_file());
+        assert!(base.join("kept/y.txt").is_file());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_layouts() {
+        use clap::Parser;
+        use template::Layout;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_layouts");
+        let _ = fs::remove_dir_all(&temp_dir);
+        for dir in ["a", "b/c"] {
+            fs::create_dir_all(temp_dir.join(dir)).unwrap();
+            fs::write(temp_dir.join(dir).join("notes.txt"), dir).unwrap();
+        }
+
+        let mut registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from(["organizer", temp_dir.to_str().unwrap(), "-r"]);
+        let mut files = scan::scan_directory(&temp_dir, &registry, &args)
+            .unwrap()
+            .files;
+        files.sort_by(|x, y| x.path.cmp(&y.path));
+        let destinations = |registry: &categories::CategoryRegistry| {
+            files
+                .iter()
+                .map(|f| cli::destination_path(f, registry).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            destinations(&registry),
+            [Path::new("Docs/notes.txt"), Path::new("Docs/notes.txt")]
+        );
+        registry.set_layout(Layout::Preserve);
+        assert_eq!(
+            destinations(&registry),
+            [
+                Path::new("Docs/a/notes.txt"),
+                Path::new("Docs/b/c/notes.txt")
+            ]
+        );
+        registry.set_layout(Layout::Flatten("__".to_string()));
+        assert_eq!(
+            destinations(&registry),
+            [
+                Path::new("Docs/a__notes.txt"),
+                Path::new("Docs/b__c__notes.txt")
+            ]
+        );
+
+        // Templates keep the subdirectory below whatever folder they render
+        assert_eq!(
+            Layout::Preserve.apply(PathBuf::from("Photos/2024/x.jpg"), Path::new("trip")),
+            Path::new("Photos/2024/trip/x.jpg")
+        );
+        assert!(
+            cli::MyOrganizer::try_parse_from(["organizer", ".", "--flatten-separator", "a/b"])
+                .is_err()
+        );
+        assert!(cli::MyOrganizer::try_parse_from([
+            "organizer",
+            ".",
+            "--preserve-structure",
+            "--flatten-separator",
+            "_"
+        ])
+        .is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_projects() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_projects");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("repo/.git")).unwrap();
+        fs::create_dir_all(temp_dir.join("repo/src")).unwrap();
+        fs::create_dir_all(temp_dir.join("unzipped/app")).unwrap();
+        fs::write(temp_dir.join("repo/src/main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.join("unzipped/app/package.json"), "{}").unwrap();
+        fs::write(temp_dir.join("unzipped/readme.txt"), "read me").unwrap();
+        assert_eq!(
+            projects::project_marker(&temp_dir.join("repo")),
+            Some(".git")
+        );
+        assert!(projects::inside_project(
+            &temp_dir,
+            &temp_dir.join("repo/src/main.rs")
+        ));
+        assert!(!projects::inside_project(
+            &temp_dir,
+            &temp_dir.join("unzipped/readme.txt")
+        ));
+
+        let scan = |extra: &[&str]| {
+            let mut argv = vec!["organizer", temp_dir.to_str().unwrap()];
+            argv.extend_from_slice(extra);
+            let args = cli::MyOrganizer::parse_from(argv);
+            let mut registry = categories::CategoryRegistry::default();
+            if args.projects == projects::ProjectMode::Move {
+                registry.enable_projects();
+            }
+            let scan = scan::scan_directory(&temp_dir, &registry, &args).unwrap();
+            (scan, registry, args)
+        };
+
+        // Projects are left alone by default instead of being torn apart
+        let (skipped, _, _) = scan(&["-r"]);
+        assert_eq!(skipped.files.len(), 1);
+        assert!(skipped.files[0].path.ends_with("unzipped/readme.txt"));
+        assert_eq!(skipped.filtered.projects, 2);
+        assert_eq!(scan(&["-r", "--projects", "scan"]).0.files.len(), 3);
+
+        // Moved whole, even without --recursive
+        let (moved, registry, args) = scan(&["--projects", "move"]);
+        assert_eq!(moved.files.len(), 1);
+        assert_eq!(moved.files[0].project, Some(".git"));
+        let mut journal = journal::Journal::disabled();
+        let outcome = cli::move_file(
+            &moved.files[0],
+            &temp_dir,
+            &registry,
+            &mut journal,
+            &args,
+            &args.conflict,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(
+            outcome,
+            cli::MoveOutcome::Moved(temp_dir.join("Projects/repo"))
+        );
+        assert!(temp_dir.join("Projects/repo/src/main.rs").is_file());
+        assert!(registry.is_category_folder("Projects"));
+
+        // A project can't be the organized directory, nor be copied whole
+        let repo = temp_dir.join("Projects/repo");
+        let args = cli::MyOrganizer::parse_from(["organizer".as_ref(), repo.as_os_str()]);
+        assert!(scan::scan_directory(&repo, &registry, &args).is_err());
+        let args = cli::MyOrganizer::parse_from([
+            "organizer",
+            "/tmp",
+            "--projects",
+            "move",
+            "--mode",
+            "copy",
+        ]);
+        assert!(args.validate().is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_whole_dirs() {
+        use clap::Parser;
+
+        let config = config::Config::parse(
+            r#"
+            [[rule]]
+            category = "Finance"
+            filename = "^Taxes"
+            "#,
+        )
+        .unwrap();
+        let mut registry = categories::CategoryRegistry::from_config(&config).unwrap();
+        registry.enable_folders();
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_dirs");
+        let _ = fs::remove_dir_all(&temp_dir);
+        for dir in ["Holiday/raw", "mixed", "empty", "Taxes 2024"] {
+            fs::create_dir_all(temp_dir.join(dir)).unwrap();
+        }
+        for file in [
+            "Holiday/a.png",
+            "Holiday/b.png",
+            "Holiday/raw/c.png",
+            "Holiday/notes.txt",
+        ] {
+            fs::write(temp_dir.join(file), "pixels").unwrap();
+        }
+        for file in [
+            "mixed/a.png",
+            "mixed/b.txt",
+            "mixed/c.zip",
+            "Taxes 2024/return.pdf",
+            "loose.txt",
+        ] {
+            fs::write(temp_dir.join(file), "data").unwrap();
+        }
+
+        let args = cli::MyOrganizer::parse_from([
+            "organizer".as_ref(),
+            temp_dir.as_os_str(),
+            "--dirs".as_ref(),
+            "--detect".as_ref(),
+            "extension".as_ref(),
+        ]);
+        let scan = scan::scan_directory(&temp_dir, &registry, &args).unwrap();
+        let entry = |name: &str| scan.files.iter().find(|f| f.path.ends_with(name)).unwrap();
+        assert_eq!(scan.files.len(), 5);
+        assert_eq!(entry("loose.txt").contents, None);
+
+        // Three of four files are images, so the folder goes with them
+        let holiday = entry("Holiday");
+        assert_eq!(holiday.category, "Multimedia");
+        assert_eq!(holiday.size, 24);
+        assert_eq!(holiday.contents.as_ref().unwrap().files, 4);
+        assert_eq!(entry("mixed").category, "Folders");
+        assert_eq!(entry("empty").category, "Folders");
+        assert_eq!(entry("Taxes 2024").category, "Finance");
+
+        let mut journal = journal::Journal::disabled();
+        let outcome = cli::move_file(
+            holiday,
+            &temp_dir,
+            &registry,
+            &mut journal,
+            &args,
+            &args.conflict,
+            &mut |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(
+            outcome,
+            cli::MoveOutcome::Moved(temp_dir.join("Multimedia/Holiday"))
+        );
+        assert!(temp_dir.join("Multimedia/Holiday/raw/c.png").is_file());
+        assert!(cli::MyOrganizer::try_parse_from(["organizer", "x", "--dirs", "-r"]).is_err());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_transfer_modes() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_modes");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let notes = temp_dir.join("notes.txt");
+        fs::write(&notes, "notes").unwrap();
+        let journal_path = temp_dir.join("state/journal.jsonl");
+        let registry = categories::CategoryRegistry::default();
+        let args = cli::MyOrganizer::parse_from(["organizer".as_ref(), temp_dir.as_os_str()]);
+        let info = cli::file_info(
+            notes.clone(),
+            &fs::metadata(&notes).unwrap(),
+            &registry,
+            &args,
+        );
+
+        let place = |mode: &str, output: &Path| {
+            let args = cli::MyOrganizer::parse_from([
+                "organizer".as_ref(),
+                temp_dir.as_os_str(),
+                "--conflict".as_ref(),
+                "dedupe".as_ref(),
+                "--mode".as_ref(),
+                mode.as_ref(),
+            ]);
+            let mut journal = journal::Journal::open(&journal_path).unwrap();
+            let mut stats = cli::OrganizeStats::new();
+            let outcome = cli::move_file(
+                &info,
+                output,
+                &registry,
+                &mut journal,
+                &args,
+                &args.conflict,
+                &mut |_, _| {},
+            )
+            .unwrap();
+            if outcome != cli::MoveOutcome::Skipped {
+                stats.add_placed(args.mode);
+            }
+            (outcome, stats)
+        };
+
+        // Copies and links leave the original in place
+        let (outcome, stats) = place("copy", &temp_dir.join("copies"));
+        let copy = temp_dir.join("copies/Docs/notes.txt");
+        assert_eq!(outcome, cli::MoveOutcome::Copied(copy.clone()));
+        assert_eq!((stats.copied, stats.moved, stats.placed()), (1, 0, 1));
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "notes");
+        assert!(notes.exists());
+
+        let (outcome, stats) = place("symlink", &temp_dir.join("links"));
+        let link = temp_dir.join("links/Docs/notes.txt");
+        assert_eq!(outcome, cli::MoveOutcome::Linked(link.clone()));
+        assert_eq!(stats.linked, 1);
+        assert_eq!(fs::read_link(&link).unwrap(), notes);
+
+        // Deduplicating against an identical copy skips instead of removing the original
+        let (outcome, _) = place("copy", &temp_dir.join("copies"));
+        assert_eq!(outcome, cli::MoveOutcome::Skipped);
+        assert!(notes.exists());
+
+        // Undo removes the link and the copy along with the directories created for them
+        assert_eq!(
+            journal::undo(&journal_path, None, false)
+                .unwrap()
+                .restored(),
+            2
+        );
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(!temp_dir.join("links").exists());
+        assert!(!temp_dir.join("copies").exists());
+        assert!(notes.exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_root() {
+        use clap::Parser;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_output");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let downloads = temp_dir.join("Downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        fs::write(downloads.join("notes.txt"), "notes").unwrap();
+
+        // An output root inside a recursively scanned tree is rejected up front
+        let nested = downloads.join("sorted");
+        let args = cli::MyOrganizer::parse_from([
+            "organizer".as_ref(),
+            "-r".as_ref(),
+            downloads.as_os_str(),
+            "--output".as_ref(),
+            nested.as_os_str(),
+        ]);
+        assert!(matches!(
+            cli::run(args),
+            Err(error::OrganizerError::InvalidPath(_))
+        ));
+        assert!(!nested.exists());
+
+        let sorted = temp_dir.join("sorted");
+        let args = cli::MyOrganizer::parse_from([
+            "organizer".as_ref(),
+            downloads.as_os_str(),
+            "--output".as_ref(),
+            sorted.as_os_str(),
+        ]);
+        let registry = categories::CategoryRegistry::default();
+        let path = downloads.join("notes.txt");
+        let info = cli::file_info(
+            path.clone(),
+            &fs::metadata(&path).unwrap(),
+            &registry,
+            &args,
+        );
+        let mut journal = journal::Journal::disabled();
+        let outcome = cli::move_file(
+            &info,
+            &sorted,
+            &registry,
+            &mut journal,
+            &args,
+            &args.conflict,
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            outcome,
+            cli::MoveOutcome::Moved(sorted.join("Docs/notes.txt"))
+        );
+        assert!(sorted.join("Docs/notes.txt").exists());
+        assert!(!downloads.join("Docs").exists());
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_rules() {
+        use clap::Parser;
+
+        let config = config::Config::parse(
+            r#"
+            [[rule]]
+            name = "Invoices"
+            category = "Finance"
+            template = "{category}/Invoices/{filename}"
+            extensions = ["pdf"]
+            filename = "(?i)invoice"
+
+            [[rule]]
+            category = "Scans"
+            parent = "Scan*"
+            "#,
+        )
+        .unwrap();
+        let registry = categories::CategoryRegistry::from_config(&config).unwrap();
+        assert!(registry.is_category_folder("Finance"));
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_rules");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("Scanner")).unwrap();
+        let args = cli::MyOrganizer::parse_from(["organizer".as_ref(), temp_dir.as_os_str()]);
+        let info = |name: &str| {
+            let path = temp_dir.join(name);
+            fs::write(&path, "%PDF-1.4").unwrap();
+            cli::file_info(
+                path.clone(),
+                &fs::metadata(&path).unwrap(),
+                &registry,
+                &args,
+            )
+        };
+
+        // First match wins, and a rule's template beats its category's
+        let invoice = info("Invoice_March.pdf");
+        assert_eq!(
+            (invoice.category.as_str(), invoice.rule.as_deref()),
+            ("Finance", Some("Invoices"))
+        );
+        assert_eq!(
+            cli::destination_path(&invoice, &registry).unwrap(),
+            Path::new("Finance/Invoices/Invoice_March.pdf")
+        );
+        let scan = info("Scanner/invoice.txt");
+        assert_eq!(
+            (scan.category.as_str(), scan.rule.as_deref()),
+            ("Scans", Some("rule 2"))
+        );
+        let report = info("report.pdf");
+        assert_eq!((report.category.as_str(), report.rule), ("Docs", None));
+
+        let metadata = fs::metadata(&report.path).unwrap();
+        let candidate = rules::Candidate::new(&report.path, report.mime_type.as_deref(), &metadata);
+        let checks = registry.rules()[0].explain(&candidate);
+        assert_eq!(
+            checks.iter().map(|c| c.passed).collect::<Vec<_>>(),
+            [true, false]
+        );
+        assert_eq!(checks[1].condition, "name matches (?i)invoice");
+
+        for invalid in [
+            "[[rule]]\ncategory = \"X\"",
+            "[[rule]]\ncategory = \"X\"\nfilename = \"(\"",
+            "[[rule]]\ncategory = \"X\"\nmin_size = \"big\"",
+        ] {
+            let config = config::Config::parse(invalid).unwrap();
+            assert!(categories::CategoryRegistry::from_config(&config).is_err());
+        }
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
     #[test]
     fn test_generate_unique_filename() {
         // Create a temporary directory for testing
@@ -79,7 +1955,7 @@ mod tests {
         fs::write(&test_file, "test").unwrap();
 
         // Generate unique filename
-        let unique = cli::generate_unique_filename(&test_file);
+        let unique = cli::generate_unique_filename(&test_file).unwrap();
 
         // Should have _1 suffix
         assert_eq!(unique.file_name().unwrap().to_str().unwrap(), "test_1.txt");
@@ -88,6 +1964,77 @@ mod tests {
         fs::remove_dir_all(&temp_dir).unwrap();
     }
 
+    #[test]
+    fn test_rename_pattern() {
+        use template::RenamePattern;
+
+        let temp_dir = std::env::temp_dir().join("organizer_test_rename_pattern");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let source = temp_dir.join("notes.txt");
+        fs::write(&source, "hello").unwrap();
+        let name = |path: PathBuf| path.file_name().unwrap().to_string_lossy().into_owned();
+        let free = |_: &Path| false;
+
+        // Compound extensions stay together, dotfiles have no extension
+        let default = RenamePattern::default();
+        for (taken, renamed) in [
+            ("archive.tar.gz", "archive_1.tar.gz"),
+            ("backup.TAR.XZ", "backup_1.TAR.XZ"),
+            ("photo.jpg", "photo_1.jpg"),
+            (".bashrc", ".bashrc_1"),
+            ("写真.txt", "写真_1.txt"),
+            ("日本語.tar.gz", "日本語_1.tar.gz"),
+            ("ü.gz", "ü_1.gz"),
+            ("README", "README_1"),
+        ] {
+            let unique =
+                cli::unique_filename(&temp_dir.join(taken), &source, &default, free).unwrap();
+            assert_eq!(name(unique), renamed);
+        }
+
+        // The counter goes on past taken names
+        let parens = RenamePattern::parse(" ({n})").unwrap();
+        let taken = temp_dir.join("notes (1).txt");
+        let unique = cli::unique_filename(&source, &source, &parens, |p| p == taken).unwrap();
+        assert_eq!(name(unique), "notes (2).txt");
+
+        // Without {n}, the counter is only added when the first name is taken
+        let hash = RenamePattern::parse("-{hash}").unwrap();
+        let short = hash::hash_file(&source).unwrap().to_hex()[..8].to_string();
+        let unique = cli::unique_filename(&source, &source, &hash, free).unwrap();
+        assert_eq!(name(unique), format!("notes-{}.txt", short));
+        let first = temp_dir.join(format!("notes-{}.txt", short));
+        let unique = cli::unique_filename(&source, &source, &hash, |p| p == first).unwrap();
+        assert_eq!(name(unique), format!("notes-{}_2.txt", short));
+
+        let timestamp = RenamePattern::parse("-{timestamp}").unwrap();
+        let unique = name(cli::unique_filename(&source, &source, &timestamp, free).unwrap());
+        assert_eq!(unique.len(), "notes-YYYYmmdd-HHMMSS.txt".len());
+
+        // Giving up instead of looping forever
+        let err = cli::unique_filename(&source, &source, &default, |_| true).unwrap_err();
+        assert!(matches!(err, error::OrganizerError::NameExhausted(_, 1000)));
+
+        for invalid in ["", "/{n}", "_{n", "_{count}"] {
+            assert!(RenamePattern::parse(invalid).is_err(), "{:?}", invalid);
+        }
+
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn test_truncate_str() {
+        assert_eq!(cli::truncate_str("notes.txt", 9), "notes.txt");
+        assert_eq!(cli::truncate_str("meeting notes.txt", 10), "meeting...");
+        // Cut between characters, not inside one
+        assert_eq!(
+            cli::truncate_str("日本語のファイル名.txt", 8),
+            "日本語のフ..."
+        );
+        assert_eq!(cli::truncate_str("ü", 1), "ü");
+    }
+
     #[test]
     fn test_organize_stats_new() {
         let stats = cli::OrganizeStats::new();
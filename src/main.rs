@@ -1,13 +1,13 @@
 use clap::Parser;
-
-mod cli;
-mod error;
-mod tui;
+use organizer::cli;
 
 fn main() {
     let args = cli::MyOrganizer::parse();
+    if let Err(e) = args.validate() {
+        e.exit();
+    }
 
-    if let Err(e) = cli::organizer_files(args) {
+    if let Err(e) = cli::run(args) {
         eprintln!("Error: {}", e);
         std::process::exit(1);
     }
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use exif::{In, Reader, Tag, Value};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    time::SystemTime,
+};
+
+/// Bytes read from each end of a PDF when looking for its document info
+const PDF_SCAN_LEN: u64 = 64 * 1024;
+
+/// Which date fills the `{year}`, `{month}` and `{day}` template placeholders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DateSource {
+    /// Last modification time
+    Modified,
+    /// Creation time, falling back to the modification time where unsupported
+    Created,
+    /// Date embedded in the file (EXIF, PDF info), falling back to the modification time
+    Metadata,
+}
+
+/// Photo metadata read from EXIF
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExifInfo {
+    /// When the picture was taken
+    pub captured: Option<NaiveDateTime>,
+    /// Camera make and model, e.g. "Canon EOS 5D"
+    pub camera: Option<String>,
+    /// Whether the picture has GPS coordinates
+    pub has_gps: bool,
+}
+
+/// Local date of a file according to `source`
+pub fn file_date(
+    path: &Path,
+    metadata: &fs::Metadata,
+    mime_type: Option<&str>,
+    exif: Option<&ExifInfo>,
+    source: DateSource,
+) -> Option<NaiveDateTime> {
+    let local = |time: SystemTime| DateTime::<Local>::from(time).naive_local();
+    let modified = || metadata.modified().ok().map(local);
+
+    match source {
+        DateSource::Modified => modified(),
+        DateSource::Created => metadata.created().ok().map(local).or_else(modified),
+        DateSource::Metadata => embedded_date(path, mime_type, exif).or_else(modified),
+    }
+}
+
+/// When the content was created according to the file itself: the EXIF capture
+/// time for photos and `/CreationDate` for PDFs
+pub fn embedded_date(
+    path: &Path,
+    mime_type: Option<&str>,
+    exif: Option<&ExifInfo>,
+) -> Option<NaiveDateTime> {
+    match mime_type? {
+        "application/pdf" => pdf_creation_date(path),
+        _ => exif.and_then(|exif| exif.captured),
+    }
+}
+
+/// Read EXIF from images in JPEG, TIFF, HEIF, PNG and WebP containers.
+///
+/// Returns `None` for other files and for images without EXIF.
+pub fn read_exif(path: &Path, mime_type: Option<&str>) -> Option<ExifInfo> {
+    if !mime_type?.starts_with("image/") {
+        return None;
+    }
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let exif = Reader::new().read_from_container(&mut reader).ok()?;
+
+    let ascii = |tag: Tag| -> Option<String> {
+        let field = exif.get_field(tag, In::PRIMARY)?;
+        let Value::Ascii(values) = &field.value else {
+            return None;
+        };
+        let text = String::from_utf8_lossy(values.first()?);
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        (!text.is_empty()).then(|| text.to_string())
+    };
+
+    let captured =
+        [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+            .into_iter()
+            .find_map(|tag| {
+                let field = exif.get_field(tag, In::PRIMARY)?;
+                let Value::Ascii(values) = &field.value else {
+                    return None;
+                };
+                let dt = exif::DateTime::from_ascii(values.first()?).ok()?;
+                NaiveDate::from_ymd_opt(dt.year.into(), dt.month.into(), dt.day.into())?
+                    .and_hms_opt(dt.hour.into(), dt.minute.into(), dt.second.into())
+            });
+
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        // Most models already start with the make ("Canon EOS 5D")
+        (Some(make), Some(model)) => {
+            let brand = make
+                .split_whitespace()
+                .next()
+                .unwrap_or(&make)
+                .to_ascii_lowercase();
+            if model.to_ascii_lowercase().starts_with(&brand) {
+                Some(model)
+            } else {
+                Some(format!("{} {}", make, model))
+            }
+        }
+        (make, model) => model.or(make),
+    };
+
+    Some(ExifInfo {
+        captured,
+        camera,
+        has_gps: exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some(),
+    })
+}
+
+/// The info dictionary lives near the end of most PDFs and near the start of
+/// linearized ones, so only those two regions are searched.
+fn pdf_creation_date(path: &Path) -> Option<NaiveDateTime> {
+    let (head, tail) = read_ends(path, PDF_SCAN_LEN).ok()?;
+    [tail, head].iter().find_map(|buf| parse_pdf_date(buf))
+}
+
+fn read_ends(path: &Path, len: u64) -> io::Result<(Vec<u8>, Vec<u8>)> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut head = Vec::new();
+    (&mut file).take(len).read_to_end(&mut head)?;
+
+    let mut tail = Vec::new();
+    if size > len {
+        file.seek(SeekFrom::Start((size - len).max(len)))?;
+        file.read_to_end(&mut tail)?;
+    }
+    Ok((head, tail))
+}
+
+/// Parse the `D:YYYYMMDDHHmmSS` string following `/CreationDate`; everything after the year is optional
+pub fn parse_pdf_date(buf: &[u8]) -> Option<NaiveDateTime> {
+    const KEY: &[u8] = b"/CreationDate";
+    let start = buf.windows(KEY.len()).position(|w| w == KEY)? + KEY.len();
+    let rest = &buf[start..];
+    let rest = &rest[rest.iter().position(|b| !b.is_ascii_whitespace())?..];
+    let rest = rest.strip_prefix(b"(")?;
+    let rest = rest.strip_prefix(b"D:").unwrap_or(rest);
+
+    let digits: Vec<u32> = rest
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .take(14)
+        .map(|b| u32::from(b - b'0'))
+        .collect();
+    if digits.len() < 4 {
+        return None;
+    }
+    let field = |index: usize, default: u32| match digits.get(index..index + 2) {
+        Some([a, b]) => a * 10 + b,
+        _ => default,
+    };
+    let year = digits[..4].iter().fold(0, |acc, d| acc * 10 + d);
+
+    NaiveDate::from_ymd_opt(year as i32, field(4, 1), field(6, 1))?.and_hms_opt(
+        field(8, 0),
+        field(10, 0),
+        field(12, 0),
+    )
+}
@@ -0,0 +1,414 @@
+use crate::categories::CategoryRegistry;
+use crate::cli::{destination_path, unique_filename, FileInfo, MoveOutcome};
+use crate::error::{OrganizerError, Result};
+use crate::hash::files_identical;
+use crate::journal::{self, ConflictAction, Journal};
+use crate::transfer::{transfer, TransferMode};
+use crate::trash::Trash;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Bumped whenever the plan format changes incompatibly
+pub const PLAN_VERSION: u32 = 1;
+
+/// What will happen to a file when its action is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resolution {
+    /// Move to a free destination
+    Move,
+    /// Replace the file already at the destination
+    Overwrite,
+    /// Move under a new name because the destination is taken
+    Rename,
+    /// Delete the source, which is identical to the file at the destination
+    /// (only when moving)
+    RemoveDuplicate,
+    /// Leave the file where it is
+    Skip,
+}
+
+/// A single intended change, with the source's size and mtime at planning
+/// time (and the destination's, for overwrites) so stale actions can be refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub category: String,
+    pub resolution: Resolution,
+    /// Why the file goes where it goes, for reviewers
+    pub reason: String,
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// Size of the file an overwrite replaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_size: Option<u64>,
+    /// Milliseconds since the Unix epoch, for the file an overwrite replaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination_modified: Option<u64>,
+}
+
+/// Every action of a run, written by `organizer plan` and executed by `organizer apply`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub version: u32,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    pub base_path: PathBuf,
+    /// Where the category folders go, when not under `base_path`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    /// Whether files are moved, copied or linked into place
+    #[serde(default)]
+    pub mode: TransferMode,
+    pub actions: Vec<PlannedAction>,
+}
+
+impl Plan {
+    /// Plan every file scanned from `base_path` into category folders under
+    /// `output`, resolving conflicts between files of the same plan as well as
+    /// with files already on disk
+    pub fn build(
+        files: &[FileInfo],
+        base_path: &Path,
+        output: &Path,
+        registry: &CategoryRegistry,
+        conflict: &str,
+        mode: TransferMode,
+    ) -> Result<Self> {
+        // Absolute paths let the plan be applied from any working directory
+        let base_path = journal::absolute(base_path);
+        let output = journal::absolute(output);
+        let mut claimed = HashSet::new();
+        let mut actions = Vec::with_capacity(files.len());
+        for file in files {
+            let mut action = plan_action(file, &output, registry, conflict, mode, &claimed)?;
+            action.source = journal::absolute(&action.source);
+            if action.resolution != Resolution::Skip {
+                claimed.insert(action.destination.clone());
+            }
+            actions.push(action);
+        }
+
+        Ok(Self {
+            version: PLAN_VERSION,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            output: (output != base_path).then_some(output),
+            base_path,
+            mode,
+            actions,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => OrganizerError::PathNotFound(path.to_path_buf()),
+            _ => OrganizerError::IoError(e),
+        })?;
+        let plan: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| OrganizerError::Plan(format!("{}: {}", path.display(), e)))?;
+        if plan.version != PLAN_VERSION {
+            return Err(OrganizerError::Plan(format!(
+                "{}: unsupported plan version {} (expected {})",
+                path.display(),
+                plan.version,
+                PLAN_VERSION
+            )));
+        }
+        Ok(plan)
+    }
+
+    pub fn write(&self, writer: impl Write) -> Result<()> {
+        let mut writer = writer;
+        serde_json::to_writer_pretty(&mut writer, self)
+            .map_err(|e| OrganizerError::Plan(e.to_string()))?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Number of actions with the given resolution
+    pub fn count(&self, resolution: Resolution) -> usize {
+        self.actions
+            .iter()
+            .filter(|a| a.resolution == resolution)
+            .count()
+    }
+}
+
+/// Decide where a file goes under `base_path` and how a conflict at the
+/// destination is resolved.
+///
+/// `claimed` holds destinations already taken by earlier actions of the same
+/// plan; a file is never overwritten or deduplicated against one of those.
+pub(crate) fn plan_action(
+    file_info: &FileInfo,
+    base_path: &Path,
+    registry: &CategoryRegistry,
+    conflict: &str,
+    mode: TransferMode,
+    claimed: &HashSet<PathBuf>,
+) -> Result<PlannedAction> {
+    let mut destination = base_path.join(destination_path(file_info, registry)?);
+    // A dangling symlink still takes up the name
+    let occupied = |path: &Path| fs::symlink_metadata(path).is_ok() || claimed.contains(path);
+    let on_disk = fs::symlink_metadata(&destination).is_ok();
+    let mut reason = category_reason(file_info, registry);
+    let whole_dir = file_info.contents.is_some();
+    // Only a file that is there can be compared with, so a dangling symlink is
+    // replaced or renamed around
+    let comparable = on_disk && !whole_dir && destination.exists();
+
+    let resolution = if !occupied(&destination) {
+        Resolution::Move
+    } else {
+        let taken = if on_disk {
+            "destination exists"
+        } else {
+            "destination is taken by another file in this plan"
+        };
+        match conflict {
+            "skip" => {
+                reason.push_str(&format!("; {}, skipped", taken));
+                Resolution::Skip
+            }
+            // A whole directory is never replaced or removed, only renamed
+            "overwrite" if on_disk && !whole_dir => {
+                reason.push_str("; replaces the existing file");
+                Resolution::Overwrite
+            }
+            "newer" | "larger" if comparable => {
+                let (source, existing) =
+                    (fs::metadata(&file_info.path)?, fs::metadata(&destination)?);
+                let (wins, comparison, quality) = if conflict == "newer" {
+                    (source.modified()? > existing.modified()?, "newer", "new")
+                } else {
+                    (source.len() > existing.len(), "larger", "large")
+                };
+                // On a tie the file already in place stays
+                if wins {
+                    reason.push_str(&format!(
+                        "; {} than the existing file, replaces it",
+                        comparison
+                    ));
+                    Resolution::Overwrite
+                } else {
+                    reason.push_str(&format!(
+                        "; existing file is at least as {}, skipped",
+                        quality
+                    ));
+                    Resolution::Skip
+                }
+            }
+            // Only a real run stops to ask
+            "ask" => {
+                reason.push_str(&format!("; {}, would ask", taken));
+                Resolution::Skip
+            }
+            "dedupe" if comparable && files_identical(&file_info.path, &destination)? => {
+                // Copies and links never touch the original, so there is nothing to remove
+                if mode.keeps_source() {
+                    reason.push_str("; identical to the existing file, skipped");
+                    Resolution::Skip
+                } else {
+                    reason.push_str("; identical to the existing file");
+                    Resolution::RemoveDuplicate
+                }
+            }
+            _ => {
+                destination = unique_filename(
+                    &destination,
+                    &file_info.path,
+                    registry.rename_pattern(),
+                    occupied,
+                )?;
+                reason.push_str(&format!("; {}, renamed", taken));
+                Resolution::Rename
+            }
+        }
+    };
+
+    let metadata = fs::symlink_metadata(&file_info.path)?;
+    let replaced = match resolution {
+        Resolution::Overwrite => Some(fs::symlink_metadata(&destination)?),
+        _ => None,
+    };
+    Ok(PlannedAction {
+        source: file_info.path.clone(),
+        category: file_info.category.clone(),
+        resolution,
+        reason,
+        size: metadata.len(),
+        modified: journal::mtime_millis(&metadata),
+        destination_size: replaced.as_ref().map(|m| m.len()),
+        destination_modified: replaced.as_ref().and_then(journal::mtime_millis),
+        destination,
+    })
+}
+
+fn category_reason(file_info: &FileInfo, registry: &CategoryRegistry) -> String {
+    if let Some(marker) = file_info.project {
+        return format!("{} (project root with {})", file_info.category, marker);
+    }
+    if let (Some(contents), None) = (&file_info.contents, &file_info.rule) {
+        return match contents.categories.get(&file_info.category) {
+            Some(count) => format!(
+                "{} (folder, {} of its {} files)",
+                file_info.category, count, contents.files
+            ),
+            None if contents.files == 0 => format!("{} (empty folder)", file_info.category),
+            None => format!(
+                "{} (folder of {} files, none of one kind)",
+                file_info.category, contents.files
+            ),
+        };
+    }
+    if let Some(rule) = file_info
+        .rule
+        .as_deref()
+        .and_then(|name| registry.rule(name))
+    {
+        return format!(
+            "{} (rule \"{}\": {})",
+            file_info.category,
+            rule.name,
+            rule.describe()
+        );
+    }
+    match &file_info.mime_type {
+        Some(mime) => format!(
+            "{} ({} detected by {})",
+            file_info.category, mime, file_info.detected_by
+        ),
+        None => format!("{} (unknown type)", file_info.category),
+    }
+}
+
+/// Why an action can no longer be applied as planned, if it can't
+pub fn check_stale(action: &PlannedAction) -> Option<String> {
+    if action.resolution == Resolution::Skip {
+        return None;
+    }
+    let metadata = match fs::symlink_metadata(&action.source) {
+        Ok(metadata) => metadata,
+        Err(_) => return Some("source no longer exists".to_string()),
+    };
+    if metadata.len() != action.size || journal::mtime_millis(&metadata) != action.modified {
+        return Some("source changed since planning".to_string());
+    }
+    match action.resolution {
+        Resolution::Move | Resolution::Rename
+            if fs::symlink_metadata(&action.destination).is_ok() =>
+        {
+            Some("destination appeared since planning".to_string())
+        }
+        Resolution::RemoveDuplicate
+            if !files_identical(&action.source, &action.destination).unwrap_or(false) =>
+        {
+            Some("destination is no longer identical".to_string())
+        }
+        // Only the file that was reviewed may be replaced; once it is gone
+        // the overwrite is a plain move
+        Resolution::Overwrite => match fs::symlink_metadata(&action.destination) {
+            Ok(replaced)
+                if Some(replaced.len()) != action.destination_size
+                    || journal::mtime_millis(&replaced) != action.destination_modified =>
+            {
+                Some("destination changed since planning".to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Carry out a planned action, journaling every change. Files it replaces or
+/// removes go to `trash`, or are deleted without one. `progress` receives the
+/// bytes copied so far whenever file contents are copied.
+pub(crate) fn apply_action(
+    action: &PlannedAction,
+    mode: TransferMode,
+    journal: &mut Journal,
+    trash: Option<&Trash>,
+    dry_run: bool,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<MoveOutcome> {
+    let conflict = match action.resolution {
+        Resolution::Skip => return Ok(MoveOutcome::Skipped),
+        Resolution::RemoveDuplicate => {
+            if !dry_run {
+                let trashed = remove(&action.source, trash)?;
+                journal.record_remove_duplicate(
+                    &action.source,
+                    &action.destination,
+                    trashed.as_deref(),
+                )?;
+            }
+            return Ok(MoveOutcome::Duplicate(action.destination.clone()));
+        }
+        Resolution::Move => ConflictAction::None,
+        Resolution::Overwrite => ConflictAction::Overwrite,
+        Resolution::Rename => ConflictAction::Rename,
+    };
+
+    if !dry_run {
+        // Create the category directory (and any template subdirectories) if needed
+        if let Some(parent) = action.destination.parent() {
+            create_dirs(parent, journal)?;
+        }
+        if conflict == ConflictAction::Overwrite
+            && fs::symlink_metadata(&action.destination).is_ok()
+        {
+            if let Some(trashed) = remove(&action.destination, trash)? {
+                journal.record_trash(&action.destination, &trashed)?;
+            }
+        }
+        transfer(mode, &action.source, &action.destination, progress)?;
+        match mode {
+            TransferMode::Move => {
+                journal.record_move(&action.source, &action.destination, conflict)?
+            }
+            _ => journal.record_create_file(&action.source, &action.destination, mode)?,
+        }
+    }
+
+    let destination = action.destination.clone();
+    Ok(match mode {
+        TransferMode::Move => MoveOutcome::Moved(destination),
+        TransferMode::Copy => MoveOutcome::Copied(destination),
+        TransferMode::Symlink | TransferMode::Hardlink => MoveOutcome::Linked(destination),
+    })
+}
+
+/// Move `path` to the trash and return where it went, or delete it for good
+/// without one
+fn remove(path: &Path, trash: Option<&Trash>) -> Result<Option<PathBuf>> {
+    match trash {
+        Some(trash) => trash.put(path).map(Some),
+        None => {
+            fs::remove_file(path)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Create `dir` and any missing parents, journaling each directory created
+fn create_dirs(dir: &Path, journal: &mut Journal) -> Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    if let Some(parent) = dir.parent() {
+        create_dirs(parent, journal)?;
+    }
+    fs::create_dir(dir)?;
+    journal.record_create_dir(dir)
+}
@@ -0,0 +1,61 @@
+use clap::ValueEnum;
+use std::{fs, path::Path};
+
+/// Category that whole projects are moved into with `--projects move`
+pub const PROJECTS_CATEGORY: &str = "Projects";
+
+/// Entries whose presence marks a directory as the root of a project, checked
+/// in this order
+pub const PROJECT_MARKERS: &[&str] = &[
+    ".git",
+    ".hg",
+    ".svn",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+    "mix.exs",
+    "pubspec.yaml",
+    "CMakeLists.txt",
+    "Package.swift",
+];
+
+/// What a recursive scan does with project directories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProjectMode {
+    /// Leave projects where they are, without looking inside
+    #[default]
+    Skip,
+    /// Move each project as a whole into the Projects category
+    Move,
+    /// Treat projects like any other directory and organize their files
+    Scan,
+}
+
+/// The marker that makes `dir` a project root, if any
+pub fn project_marker(dir: &Path) -> Option<&'static str> {
+    PROJECT_MARKERS
+        .iter()
+        .copied()
+        .find(|marker| fs::symlink_metadata(dir.join(marker)).is_ok())
+}
+
+/// Whether `path` is inside a project root below `base_path` (not counting
+/// `base_path` itself), for files found outside a scan
+pub fn inside_project(base_path: &Path, path: &Path) -> bool {
+    let relative = path.strip_prefix(base_path).unwrap_or(path);
+    let mut dir = base_path.to_path_buf();
+    let Some(parent) = relative.parent() else {
+        return false;
+    };
+    parent.components().any(|component| {
+        dir.push(component);
+        project_marker(&dir).is_some()
+    })
+}
@@ -0,0 +1,252 @@
+use crate::categories::{matches_extension, mime_matches};
+use crate::config::RuleConfig;
+use crate::error::{OrganizerError, Result};
+use crate::filter::{parse_age, parse_size};
+use crate::template::Template;
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::{
+    fs::Metadata,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// A `[[rule]]` from the config: every condition must hold for the rule to
+/// send a file to its category. Rules are tried in order before the
+/// categories' own extension and MIME lists, and the first match wins.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub category: String,
+    template: Option<Template>,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Extension(Vec<String>),
+    Mime(Vec<String>),
+    Name(Regex),
+    MinSize(u64, String),
+    MaxSize(u64, String),
+    OlderThan(Duration, String),
+    NewerThan(Duration, String),
+    Parent(GlobMatcher),
+}
+
+/// What rules are matched against
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a> {
+    pub path: &'a Path,
+    pub mime_type: Option<&'a str>,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl<'a> Candidate<'a> {
+    pub fn new(path: &'a Path, mime_type: Option<&'a str>, metadata: &Metadata) -> Self {
+        Self {
+            path,
+            mime_type,
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+/// One condition of a rule checked against a file, for `--explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// The condition, e.g. "extension is pdf"
+    pub condition: String,
+    /// What the file has instead, e.g. "invoice.pdf"
+    pub actual: String,
+    pub passed: bool,
+}
+
+impl Rule {
+    /// Build rule number `index` (counting from 1) from its config table
+    pub fn from_config(config: &RuleConfig, index: usize) -> Result<Self> {
+        let name = config
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("rule {}", index));
+        let invalid =
+            |message: String| OrganizerError::Config(format!("rule '{}': {}", name, message));
+
+        if config.category.trim().is_empty() {
+            return Err(invalid("category cannot be empty".to_string()));
+        }
+
+        let mut conditions = Vec::new();
+        if !config.extensions.is_empty() {
+            conditions.push(Condition::Extension(
+                config
+                    .extensions
+                    .iter()
+                    .map(|e| e.trim_start_matches('.').to_ascii_lowercase())
+                    .collect(),
+            ));
+        }
+        if !config.mime.is_empty() {
+            conditions.push(Condition::Mime(
+                config.mime.iter().map(|m| m.to_ascii_lowercase()).collect(),
+            ));
+        }
+        if let Some(pattern) = &config.filename {
+            let regex = Regex::new(pattern)
+                .map_err(|e| invalid(format!("invalid filename regex: {}", e)))?;
+            conditions.push(Condition::Name(regex));
+        }
+        if let Some(size) = &config.min_size {
+            conditions.push(Condition::MinSize(
+                parse_size(size).map_err(&invalid)?,
+                size.clone(),
+            ));
+        }
+        if let Some(size) = &config.max_size {
+            conditions.push(Condition::MaxSize(
+                parse_size(size).map_err(&invalid)?,
+                size.clone(),
+            ));
+        }
+        if let Some(age) = &config.older_than {
+            conditions.push(Condition::OlderThan(
+                parse_age(age).map_err(&invalid)?,
+                age.clone(),
+            ));
+        }
+        if let Some(age) = &config.newer_than {
+            conditions.push(Condition::NewerThan(
+                parse_age(age).map_err(&invalid)?,
+                age.clone(),
+            ));
+        }
+        if let Some(pattern) = &config.parent {
+            let glob =
+                Glob::new(pattern).map_err(|e| invalid(format!("invalid parent glob: {}", e)))?;
+            conditions.push(Condition::Parent(glob.compile_matcher()));
+        }
+
+        // A rule without conditions would swallow every file after it
+        if conditions.is_empty() {
+            return Err(invalid("has no conditions".to_string()));
+        }
+
+        Ok(Self {
+            name,
+            category: config.category.clone(),
+            template: config
+                .template
+                .as_deref()
+                .map(Template::parse)
+                .transpose()?,
+            conditions,
+        })
+    }
+
+    /// Destination template of this rule, overriding its category's
+    pub fn template(&self) -> Option<&Template> {
+        self.template.as_ref()
+    }
+
+    pub fn matches(&self, candidate: &Candidate) -> bool {
+        self.conditions.iter().all(|c| c.check(candidate).passed)
+    }
+
+    /// Every condition of the rule checked against a file
+    pub fn explain(&self, candidate: &Candidate) -> Vec<Check> {
+        self.conditions.iter().map(|c| c.check(candidate)).collect()
+    }
+
+    /// The conditions in one line, e.g. "extension is pdf and name matches invoice"
+    pub fn describe(&self) -> String {
+        self.conditions
+            .iter()
+            .map(Condition::describe)
+            .collect::<Vec<_>>()
+            .join(" and ")
+    }
+}
+
+impl Condition {
+    fn describe(&self) -> String {
+        match self {
+            Condition::Extension(extensions) => format!("extension is {}", extensions.join(" or ")),
+            Condition::Mime(patterns) => format!("type is {}", patterns.join(" or ")),
+            Condition::Name(regex) => format!("name matches {}", regex),
+            Condition::MinSize(_, text) => format!("size is at least {}", text),
+            Condition::MaxSize(_, text) => format!("size is at most {}", text),
+            Condition::OlderThan(_, text) => format!("modified more than {} ago", text),
+            Condition::NewerThan(_, text) => format!("modified less than {} ago", text),
+            Condition::Parent(glob) => format!("parent directory matches {}", glob.glob()),
+        }
+    }
+
+    fn check(&self, candidate: &Candidate) -> Check {
+        let file_name = candidate
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        // Files with a modification time in the future count as brand new
+        let age = candidate
+            .modified
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+
+        let (passed, actual) = match self {
+            Condition::Extension(extensions) => {
+                (matches_extension(candidate.path, extensions), file_name)
+            }
+            Condition::Mime(patterns) => match candidate.mime_type {
+                Some(mime) => (
+                    patterns.iter().any(|p| mime_matches(p, mime)),
+                    mime.to_string(),
+                ),
+                None => (false, "unknown type".to_string()),
+            },
+            Condition::Name(regex) => (regex.is_match(&file_name), file_name),
+            Condition::MinSize(min, _) => {
+                (candidate.size >= *min, format!("{} bytes", candidate.size))
+            }
+            Condition::MaxSize(max, _) => {
+                (candidate.size <= *max, format!("{} bytes", candidate.size))
+            }
+            Condition::OlderThan(limit, _) => (age > *limit, format_age(age)),
+            Condition::NewerThan(limit, _) => (age < *limit, format_age(age)),
+            Condition::Parent(glob) => {
+                let parent = candidate
+                    .path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                (glob.is_match(&parent), format!("in {}", parent))
+            }
+        };
+
+        Check {
+            condition: self.describe(),
+            actual,
+            passed,
+        }
+    }
+}
+
+/// Rough age such as "3 days ago", for explaining age conditions
+fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (count, unit) = match seconds {
+        s if s >= 24 * 60 * 60 => (s / (24 * 60 * 60), "day"),
+        s if s >= 60 * 60 => (s / (60 * 60), "hour"),
+        s if s >= 60 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    format!(
+        "modified {} {}{} ago",
+        count,
+        unit,
+        if count == 1 { "" } else { "s" }
+    )
+}
@@ -0,0 +1,430 @@
+use crate::categories::CategoryRegistry;
+use crate::cli::{file_info, FileInfo, MyOrganizer};
+use crate::detect::DetectionMethod;
+use crate::duplicates::DUPLICATES_FOLDER;
+use crate::error::{OrganizerError, Result};
+use crate::filter::{Filtered, FilteredCounts, IgnoreStack, ScanFilter, IGNORE_FILE};
+use crate::folders::{DirContents, FOLDERS_CATEGORY};
+use crate::journal::Journal;
+use crate::projects::{project_marker, ProjectMode, PROJECTS_CATEGORY};
+use crate::rules::Candidate;
+use clap::ValueEnum;
+use colored::Colorize;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, Metadata},
+    path::{Path, PathBuf},
+};
+
+/// Files found by a scan, and how many were left out by the filters
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    pub files: Vec<FileInfo>,
+    pub filtered: FilteredCounts,
+}
+
+/// Collect and categorize the files to organize under `dir`, honouring the
+/// scan options and `.organizerignore` files
+pub(crate) fn scan_directory(
+    dir: &Path,
+    registry: &CategoryRegistry,
+    args: &MyOrganizer,
+) -> Result<ScanResult> {
+    collect(dir, registry, args, false)
+}
+
+/// Like [`scan_directory`], but also descends into category folders, where
+/// the organized copy of a duplicate lives
+pub(crate) fn scan_with_category_folders(
+    dir: &Path,
+    registry: &CategoryRegistry,
+    args: &MyOrganizer,
+) -> Result<ScanResult> {
+    collect(dir, registry, args, true)
+}
+
+fn collect(
+    dir: &Path,
+    registry: &CategoryRegistry,
+    args: &MyOrganizer,
+    category_folders: bool,
+) -> Result<ScanResult> {
+    if args.verbose {
+        println!("{}", "Scanning directory...".bright_cyan());
+    }
+
+    // Subdirectories that are projects are skipped or moved whole below, but
+    // the organized directory itself can only be refused
+    if args.projects != ProjectMode::Scan {
+        if let Some(marker) = project_marker(dir) {
+            return Err(OrganizerError::InvalidPath(format!(
+                "{} is a project directory (it has {}), use --projects scan to organize it anyway",
+                dir.display(),
+                marker
+            )));
+        }
+    }
+
+    let mut scanner = Scanner {
+        base_path: dir,
+        canonical_base: fs::canonicalize(dir)?,
+        registry,
+        args,
+        category_folders,
+        filter: scan_filter(args)?,
+        ignores: IgnoreStack::default(),
+        visited_dirs: HashSet::from([file_id(dir, &fs::metadata(dir)?)]),
+        seen_files: HashSet::new(),
+        files: Vec::new(),
+        filtered: FilteredCounts::default(),
+    };
+    scanner.scan(dir)?;
+
+    if args.verbose {
+        print!(
+            "Found {} files",
+            scanner.files.len().to_string().bright_green().bold()
+        );
+        if scanner.filtered.total() > 0 {
+            print!(
+                " ({} filtered out: {})",
+                scanner.filtered.total().to_string().bright_yellow(),
+                scanner.filtered.describe()
+            );
+        }
+        println!("\n");
+    }
+
+    Ok(ScanResult {
+        files: scanner.files,
+        filtered: scanner.filtered,
+    })
+}
+
+/// Remove the directories under `base_path` that moving the `vacated` files
+/// out of left empty, deepest first, journaling each removal. `base_path`
+/// itself, category folders and the duplicates folder are never removed.
+///
+/// A dry run removes nothing but returns the directories that would go, by
+/// treating the vacated files as already gone.
+pub(crate) fn prune_empty_dirs(
+    base_path: &Path,
+    vacated: &[PathBuf],
+    registry: &CategoryRegistry,
+    journal: &mut Journal,
+    dry_run: bool,
+) -> Result<Vec<PathBuf>> {
+    let mut candidates = BTreeSet::new();
+    for file in vacated {
+        for dir in file.ancestors().skip(1) {
+            if dir == base_path || !dir.starts_with(base_path) {
+                break;
+            }
+            candidates.insert(dir.to_path_buf());
+        }
+    }
+
+    let mut gone: HashSet<PathBuf> = vacated.iter().cloned().collect();
+    let mut removed = Vec::new();
+    // Deeper directories first, so a parent is checked after its children
+    let mut candidates: Vec<_> = candidates.into_iter().collect();
+    candidates.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    for dir in candidates {
+        let protected = dir.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            registry.is_category_folder(&name) || name == DUPLICATES_FOLDER
+        });
+        let is_empty = fs::read_dir(&dir)
+            .map(|mut entries| entries.all(|e| e.is_ok_and(|e| gone.contains(&e.path()))))
+            .unwrap_or(false);
+        if protected || !is_empty {
+            continue;
+        }
+        if !dry_run {
+            fs::remove_dir(&dir)?;
+            journal.record_remove_dir(&dir)?;
+        }
+        gone.insert(dir.clone());
+        removed.push(dir);
+    }
+    Ok(removed)
+}
+
+/// Filter built from the `--include`, `--exclude`, size, age, hidden and depth options
+pub(crate) fn scan_filter(args: &MyOrganizer) -> Result<ScanFilter> {
+    Ok(ScanFilter::new(&args.include, &args.exclude)?
+        .with_size(args.min_size, args.max_size)
+        .with_age(args.older_than, args.newer_than)
+        .with_hidden(args.hidden)
+        .with_max_depth(args.max_depth))
+}
+
+/// Which symbolic links a scan follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FollowSymlinks {
+    /// Follow no links: a link to a file is moved itself and keeps pointing at its target
+    #[default]
+    Never,
+    /// Organize the files that links point to instead of the links
+    Files,
+    /// Like `files`, and also scan the directories that links point to
+    All,
+}
+
+#[cfg(unix)]
+type FileId = (u64, u64);
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+/// Device and inode of a directory, to notice two paths leading to the same one
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &Metadata) -> FileId {
+    use std::os::unix::fs::MetadataExt;
+    (metadata.dev(), metadata.ino())
+}
+
+/// Canonical path of a directory, where device and inode numbers aren't available
+#[cfg(not(unix))]
+fn file_id(path: &Path, _metadata: &Metadata) -> FileId {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+struct Scanner<'a> {
+    base_path: &'a Path,
+    /// `base_path` with links resolved, which followed links must stay inside
+    canonical_base: PathBuf,
+    registry: &'a CategoryRegistry,
+    args: &'a MyOrganizer,
+    /// Whether category folders are scanned like any other directory
+    category_folders: bool,
+    filter: ScanFilter,
+    ignores: IgnoreStack,
+    /// Directories scanned so far, so symlink loops are entered only once
+    visited_dirs: HashSet<FileId>,
+    /// Canonical paths of the files found so far when following links, so a
+    /// file reached through a link and directly is organized once
+    seen_files: HashSet<PathBuf>,
+    files: Vec<FileInfo>,
+    filtered: FilteredCounts,
+}
+
+impl Scanner<'_> {
+    fn scan(&mut self, current_dir: &Path) -> Result<()> {
+        self.ignores.enter(current_dir)?;
+        let result = self.scan_entries(current_dir);
+        self.ignores.leave();
+        result
+    }
+
+    fn scan_entries(&mut self, current_dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(current_dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            // Describes a symlink itself rather than its target
+            let metadata = entry.metadata()?;
+
+            if metadata.is_symlink() {
+                self.scan_symlink(path)?;
+            } else if metadata.is_file() {
+                self.scan_file(&path, path.clone(), &metadata)?;
+            } else if metadata.is_dir() {
+                self.scan_subdir(&path, &metadata)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a link according to `--follow-symlinks`. Broken links and, unless
+    /// following everything, links to directories are left alone. A link leading
+    /// outside the organized directory is never followed, so nothing is moved out
+    /// of wherever its target lives: a link to a file moves as a link instead.
+    fn scan_symlink(&mut self, path: PathBuf) -> Result<()> {
+        let relative = path.strip_prefix(self.base_path).unwrap_or(&path);
+        let Ok(target_metadata) = fs::metadata(&path) else {
+            self.report_skipped(relative, "broken symlink");
+            return Ok(());
+        };
+        let target = fs::canonicalize(&path)?;
+        let inside = target.starts_with(&self.canonical_base);
+
+        if target_metadata.is_file() {
+            let target = match self.args.follow_symlinks {
+                FollowSymlinks::Files | FollowSymlinks::All if inside => target,
+                _ => path.clone(),
+            };
+            self.scan_file(&path, target, &target_metadata)?;
+        } else if target_metadata.is_dir() && self.args.recursive {
+            if self.args.follow_symlinks != FollowSymlinks::All {
+                self.report_skipped(relative, "symlinked directory");
+            } else if !inside {
+                self.report_skipped(
+                    relative,
+                    "symlinked directory outside the organized directory",
+                );
+            } else {
+                self.scan_subdir(&path, &target_metadata)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the file found at `path`, which is `target` itself unless
+    /// `target` is the file a followed link at `path` points to
+    fn scan_file(&mut self, path: &Path, target: PathBuf, metadata: &Metadata) -> Result<()> {
+        if path.file_name().is_some_and(|name| name == IGNORE_FILE) {
+            return Ok(());
+        }
+        let relative = path.strip_prefix(self.base_path).unwrap_or(path);
+        let filtered = if !self.filter.allows_hidden(relative, false) {
+            self.report_skipped(relative, "hidden file");
+            Some(Filtered::Hidden)
+        } else if !self.filter.allows_file(relative) || self.ignores.is_ignored(path, false) {
+            Some(Filtered::Pattern)
+        } else {
+            self.filter.check_limits(metadata)
+        };
+        if let Some(reason) = filtered {
+            self.filtered.add(reason);
+            return Ok(());
+        }
+
+        // Links left as links are each organized, wherever they point
+        if self.args.follow_symlinks != FollowSymlinks::Never
+            && !target.is_symlink()
+            && !self.seen_files.insert(fs::canonicalize(&target)?)
+        {
+            self.report_skipped(relative, "already found through another path");
+            return Ok(());
+        }
+        let mut info = file_info(target, metadata, self.registry, self.args);
+        info.subdir = relative.parent().unwrap_or(Path::new("")).to_path_buf();
+        self.files.push(info);
+        Ok(())
+    }
+
+    fn scan_subdir(&mut self, path: &Path, metadata: &Metadata) -> Result<()> {
+        // Only whole projects and directories are picked up from subdirectories
+        // without --recursive
+        if !self.args.recursive && !self.args.dirs && self.args.projects != ProjectMode::Move {
+            return Ok(());
+        }
+        let relative = path.strip_prefix(self.base_path).unwrap_or(path);
+
+        // Don't recurse into category folders we create
+        let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if (self.registry.is_category_folder(&dir_name) && !self.category_folders)
+            || dir_name == DUPLICATES_FOLDER
+            || !self.filter.allows_dir(relative)
+            || self.ignores.is_ignored(path, true)
+        {
+            return Ok(());
+        }
+        if !self.filter.allows_hidden(relative, true) {
+            self.report_skipped(relative, "hidden directory");
+            return Ok(());
+        }
+        if self.args.projects != ProjectMode::Scan {
+            if let Some(marker) = project_marker(path) {
+                self.add_project(path, marker);
+                return Ok(());
+            }
+        }
+        if self.args.dirs {
+            self.add_dir(path, metadata);
+            return Ok(());
+        }
+        if !self.args.recursive {
+            return Ok(());
+        }
+        if !self.filter.within_depth(relative) {
+            self.report_skipped(relative, "beyond --max-depth");
+            return Ok(());
+        }
+        if !self.visited_dirs.insert(file_id(path, metadata)) {
+            self.report_skipped(relative, "already scanned, symlink loop");
+            return Ok(());
+        }
+        self.scan(path)
+    }
+
+    /// Skip a project directory, or collect it to be moved as a whole
+    fn add_project(&mut self, path: &Path, marker: &'static str) {
+        let relative = path.strip_prefix(self.base_path).unwrap_or(path);
+        if self.args.projects == ProjectMode::Skip {
+            self.report_skipped(relative, &format!("project, has {}", marker));
+            self.filtered.add(Filtered::Project);
+            return;
+        }
+        if !self.filter.allows_file(relative) {
+            self.filtered.add(Filtered::Pattern);
+            return;
+        }
+        let contents = DirContents::count(path);
+        self.files.push(FileInfo {
+            path: path.to_path_buf(),
+            mime_type: None,
+            detected_by: DetectionMethod::Unknown,
+            category: PROJECTS_CATEGORY.to_string(),
+            subdir: relative.parent().unwrap_or(Path::new("")).to_path_buf(),
+            rule: None,
+            project: Some(marker),
+            size: contents.size,
+            contents: Some(contents),
+            date: None,
+            exif: None,
+            tags: None,
+        });
+    }
+
+    /// Collect a directory to be moved as a whole with `--dirs`, into the
+    /// category of the first rule matching the directory itself, else the one
+    /// most of its files belong to, else Folders
+    fn add_dir(&mut self, path: &Path, metadata: &Metadata) {
+        let relative = path.strip_prefix(self.base_path).unwrap_or(path);
+        if !self.filter.allows_file(relative) {
+            self.filtered.add(Filtered::Pattern);
+            return;
+        }
+        let contents = DirContents::classify(path, self.registry, self.args.detect);
+        let candidate = Candidate {
+            path,
+            mime_type: None,
+            size: contents.size,
+            modified: metadata.modified().ok(),
+        };
+        let rule = self.registry.rules().iter().find(|r| r.matches(&candidate));
+        let category = match rule {
+            Some(rule) => rule.category.clone(),
+            None => contents
+                .dominant_category(self.registry.fallback())
+                .unwrap_or(FOLDERS_CATEGORY)
+                .to_string(),
+        };
+        self.files.push(FileInfo {
+            path: path.to_path_buf(),
+            mime_type: None,
+            detected_by: DetectionMethod::Unknown,
+            category,
+            subdir: relative.parent().unwrap_or(Path::new("")).to_path_buf(),
+            rule: rule.map(|r| r.name.clone()),
+            project: None,
+            size: contents.size,
+            contents: Some(contents),
+            date: None,
+            exif: None,
+            tags: None,
+        });
+    }
+
+    fn report_skipped(&self, relative: &Path, reason: &str) {
+        if self.args.verbose {
+            println!(
+                "  {} {} ({})",
+                "Skipping".bright_black(),
+                relative.display(),
+                reason
+            );
+        }
+    }
+}
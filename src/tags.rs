@@ -0,0 +1,463 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Largest tag frame or metadata block read into memory; bigger ones are cover art and skipped
+const MAX_FIELD_LEN: u64 = 1024 * 1024;
+
+/// Song information read from an audio file's tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AudioTags {
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    pub track: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    Track,
+}
+
+impl AudioTags {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Store a tag value; the first non-empty value for a field wins
+    fn set(&mut self, field: Field, value: &str) {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if value.is_empty() {
+            return;
+        }
+        let slot = match field {
+            Field::Artist => &mut self.artist,
+            Field::AlbumArtist => &mut self.album_artist,
+            Field::Album => &mut self.album,
+            Field::Title => &mut self.title,
+            Field::Track => {
+                // "3/12" means track 3 of 12
+                let number = value.split('/').next().unwrap_or_default().trim();
+                if self.track.is_none() {
+                    self.track = number.parse().ok().filter(|&n| n > 0);
+                }
+                return;
+            }
+        };
+        if slot.is_none() {
+            *slot = Some(value.to_string());
+        }
+    }
+}
+
+/// Read ID3v2/ID3v1 (MP3), Vorbis comment (FLAC, Ogg Vorbis, Opus) or MP4 (M4A) tags.
+///
+/// Returns `None` for files that are not audio or have no usable tags.
+pub fn read_tags(path: &Path, mime_type: Option<&str>) -> Option<AudioTags> {
+    if !mime_type?.starts_with("audio/") {
+        return None;
+    }
+    let mut reader = BufReader::new(File::open(path).ok()?);
+
+    let mut magic = Vec::with_capacity(12);
+    (&mut reader).take(12).read_to_end(&mut magic).ok()?;
+    reader.seek(SeekFrom::Start(0)).ok()?;
+
+    let tags = if magic.starts_with(b"ID3") {
+        read_id3v2(&mut reader)
+    } else if magic.starts_with(b"fLaC") {
+        read_flac(&mut reader)
+    } else if magic.starts_with(b"OggS") {
+        read_ogg(&mut reader)
+    } else if magic.get(4..8) == Some(b"ftyp") {
+        read_mp4(&mut reader)
+    } else {
+        Ok(AudioTags::default())
+    };
+
+    // Unparseable or empty tags fall back to ID3v1, which some encoders add to any format
+    let mut tags = tags.unwrap_or_default();
+    if tags.is_empty() {
+        tags = read_id3v1(&mut reader).unwrap_or_default();
+    }
+    (!tags.is_empty()).then_some(tags)
+}
+
+fn read_id3v2<R: Read + Seek>(reader: &mut R) -> io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+    let mut header = [0u8; 10];
+    reader.read_exact(&mut header)?;
+    let version = header[3];
+    let flags = header[5];
+    let tag_end = 10 + u64::from(syncsafe(&header[6..10]));
+    if !(2..=4).contains(&version) {
+        return Ok(tags);
+    }
+
+    // Skip the extended header
+    if flags & 0x40 != 0 && version >= 3 {
+        let mut size = [0u8; 4];
+        reader.read_exact(&mut size)?;
+        let skip = match version {
+            4 => syncsafe(&size).saturating_sub(4),
+            _ => u32::from_be_bytes(size),
+        };
+        reader.seek(SeekFrom::Current(i64::from(skip)))?;
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut frame = [0u8; 10];
+    // A frame cut short by the end of the file ends the tag, keeping the
+    // fields read before it
+    while reader.stream_position()? + header_len as u64 <= tag_end {
+        if reader.read_exact(&mut frame[..header_len]).is_err() {
+            break;
+        }
+        if frame[0] == 0 {
+            break; // Padding
+        }
+        let size = u64::from(match version {
+            2 => u32::from_be_bytes([0, frame[3], frame[4], frame[5]]),
+            3 => u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]),
+            _ => syncsafe(&frame[4..8]),
+        });
+        let frame_flags = if version == 2 {
+            0
+        } else {
+            u16::from_be_bytes([frame[8], frame[9]])
+        };
+
+        let field = match &frame[..id_len] {
+            b"TPE1" | b"TP1" => Some(Field::Artist),
+            b"TPE2" | b"TP2" => Some(Field::AlbumArtist),
+            b"TALB" | b"TAL" => Some(Field::Album),
+            b"TIT2" | b"TT2" => Some(Field::Title),
+            b"TRCK" | b"TRK" => Some(Field::Track),
+            _ => None,
+        };
+        let Some(field) = field.filter(|_| size <= MAX_FIELD_LEN) else {
+            reader.seek(SeekFrom::Current(size as i64))?;
+            continue;
+        };
+
+        let mut data = vec![0u8; size as usize];
+        if reader.read_exact(&mut data).is_err() {
+            break;
+        }
+        if version == 4 {
+            if frame_flags & 0x000c != 0 {
+                continue; // Compressed or encrypted
+            }
+            if frame_flags & 0x0001 != 0 {
+                // Data length indicator
+                data.drain(..data.len().min(4));
+            }
+            if frame_flags & 0x0002 != 0 {
+                data = remove_unsynchronisation(&data);
+            }
+        } else if flags & 0x80 != 0 {
+            data = remove_unsynchronisation(&data);
+        }
+        tags.set(field, &decode_id3_text(&data));
+    }
+
+    Ok(tags)
+}
+
+/// ID3v1 is a fixed 128-byte block at the very end of the file
+fn read_id3v1<R: Read + Seek>(reader: &mut R) -> io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+    if reader.seek(SeekFrom::End(0))? < 128 {
+        return Ok(tags);
+    }
+    reader.seek(SeekFrom::End(-128))?;
+    let mut block = [0u8; 128];
+    reader.read_exact(&mut block)?;
+    if !block.starts_with(b"TAG") {
+        return Ok(tags);
+    }
+
+    tags.set(Field::Title, &latin1(&block[3..33]));
+    tags.set(Field::Artist, &latin1(&block[33..63]));
+    tags.set(Field::Album, &latin1(&block[63..93]));
+    // ID3v1.1 keeps the track number in the last byte of the comment
+    if block[125] == 0 && block[126] != 0 {
+        tags.track = Some(u32::from(block[126]));
+    }
+    Ok(tags)
+}
+
+fn read_flac<R: Read + Seek>(reader: &mut R) -> io::Result<AudioTags> {
+    const VORBIS_COMMENT: u8 = 4;
+
+    reader.seek(SeekFrom::Start(4))?;
+    loop {
+        let mut header = [0u8; 4];
+        reader.read_exact(&mut header)?;
+        let is_last = header[0] & 0x80 != 0;
+        let len = u64::from(u32::from_be_bytes([0, header[1], header[2], header[3]]));
+
+        if header[0] & 0x7f == VORBIS_COMMENT && len <= MAX_FIELD_LEN {
+            // A block cut short still holds the comments before the cut
+            let mut block = Vec::new();
+            reader.take(len).read_to_end(&mut block)?;
+            return Ok(parse_vorbis_comments(&block));
+        }
+        if is_last {
+            return Ok(AudioTags::default());
+        }
+        reader.seek(SeekFrom::Current(len as i64))?;
+    }
+}
+
+/// The comments are the second packet of the stream, which may span several pages
+fn read_ogg<R: Read + Seek>(reader: &mut R) -> io::Result<AudioTags> {
+    let mut packets: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut total = 0u64;
+
+    while packets.len() < 3 && total < MAX_FIELD_LEN {
+        let mut header = [0u8; 27];
+        reader.read_exact(&mut header)?;
+        if !header.starts_with(b"OggS") {
+            break;
+        }
+        let mut lacing = vec![0u8; usize::from(header[26])];
+        reader.read_exact(&mut lacing)?;
+        for len in lacing {
+            let mut segment = vec![0u8; usize::from(len)];
+            reader.read_exact(&mut segment)?;
+            total += u64::from(len);
+            if let Some(packet) = packets.last_mut() {
+                packet.extend_from_slice(&segment);
+            }
+            // A segment shorter than 255 bytes ends the packet
+            if len < 255 {
+                packets.push(Vec::new());
+            }
+        }
+    }
+
+    let comments = packets.get(1).and_then(|packet| {
+        packet
+            .strip_prefix(b"\x03vorbis")
+            .or_else(|| packet.strip_prefix(b"OpusTags"))
+    });
+    Ok(comments.map(parse_vorbis_comments).unwrap_or_default())
+}
+
+fn parse_vorbis_comments(data: &[u8]) -> AudioTags {
+    let mut tags = AudioTags::default();
+    let mut reader = ByteReader { data, pos: 0 };
+
+    let Some(count) = reader
+        .u32_le()
+        .and_then(|vendor_len| reader.take(vendor_len))
+        .and_then(|_| reader.u32_le())
+    else {
+        return tags;
+    };
+
+    for _ in 0..count {
+        let Some(comment) = reader.u32_le().and_then(|len| reader.take(len)) else {
+            break;
+        };
+        let comment = String::from_utf8_lossy(comment);
+        let Some((key, value)) = comment.split_once('=') else {
+            continue;
+        };
+        let field = match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => Field::Artist,
+            "ALBUMARTIST" | "ALBUM ARTIST" => Field::AlbumArtist,
+            "ALBUM" => Field::Album,
+            "TITLE" => Field::Title,
+            "TRACKNUMBER" => Field::Track,
+            _ => continue,
+        };
+        tags.set(field, value);
+    }
+    tags
+}
+
+/// Bounds-checked reads from a buffer
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u32_le(&mut self) -> Option<usize> {
+        let bytes = self.take(4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+}
+
+/// iTunes-style tags live in `moov/udta/meta/ilst`
+fn read_mp4<R: Read + Seek>(reader: &mut R) -> io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+    let end = reader.seek(SeekFrom::End(0))?;
+
+    let mut range = (0, end);
+    for name in [b"moov", b"udta", b"meta", b"ilst"] {
+        let Some((start, end)) = find_atom(reader, range, name)? else {
+            return Ok(tags);
+        };
+        range = (start, end);
+        if name == b"meta" {
+            // `meta` is a full box with four bytes of version and flags
+            range.0 += 4;
+        }
+    }
+
+    for (name, start, end) in child_atoms(reader, range)? {
+        let field = match &name {
+            b"\xa9ART" => Field::Artist,
+            b"aART" => Field::AlbumArtist,
+            b"\xa9alb" => Field::Album,
+            b"\xa9nam" => Field::Title,
+            b"trkn" => Field::Track,
+            _ => continue,
+        };
+        let Some((data_start, data_end)) = find_atom(reader, (start, end), b"data")? else {
+            continue;
+        };
+        // Skip the type indicator and locale
+        let len = data_end.saturating_sub(data_start + 8);
+        if len > MAX_FIELD_LEN {
+            continue;
+        }
+        reader.seek(SeekFrom::Start(data_start + 8))?;
+        let mut payload = vec![0u8; len as usize];
+        reader.read_exact(&mut payload)?;
+
+        match field {
+            Field::Track => {
+                if let Some(&[hi, lo]) = payload.get(2..4) {
+                    tags.set(field, &u16::from_be_bytes([hi, lo]).to_string());
+                }
+            }
+            _ => tags.set(field, &String::from_utf8_lossy(&payload)),
+        }
+    }
+
+    Ok(tags)
+}
+
+fn find_atom<R: Read + Seek>(
+    reader: &mut R,
+    range: (u64, u64),
+    name: &[u8; 4],
+) -> io::Result<Option<(u64, u64)>> {
+    Ok(child_atoms(reader, range)?
+        .into_iter()
+        .find(|(atom, _, _)| atom == name)
+        .map(|(_, start, end)| (start, end)))
+}
+
+/// Name and content range of every atom directly inside `range`
+fn child_atoms<R: Read + Seek>(
+    reader: &mut R,
+    (start, end): (u64, u64),
+) -> io::Result<Vec<([u8; 4], u64, u64)>> {
+    let mut atoms = Vec::new();
+    let mut pos = start;
+    while pos + 8 <= end {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let mut size = u64::from(u32::from_be_bytes([
+            header[0], header[1], header[2], header[3],
+        ]));
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            // Extends to the end of the enclosing atom
+            size = end - pos;
+        }
+        if size < header_len {
+            break;
+        }
+        let name = [header[4], header[5], header[6], header[7]];
+        // A size running past the enclosing atom is cut short, and ends the
+        // walk so a crafted 64-bit size can't overflow or move it backwards
+        let next = pos.checked_add(size).filter(|&next| next <= end);
+        atoms.push((name, pos + header_len, next.unwrap_or(end)));
+        match next {
+            Some(next) => pos = next,
+            None => break,
+        }
+    }
+    Ok(atoms)
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |acc, &b| (acc << 7) | u32::from(b & 0x7f))
+}
+
+/// Undo ID3 unsynchronisation, which inserts a zero byte after every 0xFF
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = 0u8;
+    for &byte in data {
+        if !(previous == 0xff && byte == 0) {
+            out.push(byte);
+        }
+        previous = byte;
+    }
+    out
+}
+
+/// Decode an ID3v2 text frame, keeping only the first of multiple values
+fn decode_id3_text(data: &[u8]) -> String {
+    let Some((&encoding, text)) = data.split_first() else {
+        return String::new();
+    };
+    match encoding {
+        0 => latin1(text),
+        1 => match text {
+            [0xff, 0xfe, rest @ ..] => utf16(rest, u16::from_le_bytes),
+            [0xfe, 0xff, rest @ ..] => utf16(rest, u16::from_be_bytes),
+            _ => utf16(text, u16::from_le_bytes),
+        },
+        2 => utf16(text, u16::from_be_bytes),
+        _ => {
+            let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+            String::from_utf8_lossy(&text[..end]).into_owned()
+        }
+    }
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| char::from(b))
+        .collect()
+}
+
+fn utf16(bytes: &[u8], decode: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| decode([pair[0], pair[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
@@ -0,0 +1,335 @@
+use crate::cli::FileInfo;
+use crate::error::{OrganizerError, Result};
+use chrono::{Datelike, NaiveDateTime};
+use std::path::{Path, PathBuf};
+
+/// Placeholders a destination template may use
+pub const PLACEHOLDERS: &[&str] = &[
+    "category",
+    "year",
+    "month",
+    "day",
+    "name",
+    "ext",
+    "filename",
+    "exif.year",
+    "exif.month",
+    "exif.day",
+    "exif.camera",
+    "artist",
+    "album",
+    "title",
+    "track",
+];
+
+/// Layout used by `--music`
+pub const MUSIC_TEMPLATE: &str = "{category}/{artist}/{album}/{track:02} - {title}.{ext}";
+
+/// Longest placeholder value in bytes, leaving room for the rest of the file name
+const MAX_VALUE_LEN: usize = 200;
+
+/// Value used when a placeholder has nothing to fill it, such as a missing date
+const UNKNOWN: &str = "Unknown";
+
+/// What becomes of the directory a file was found in, relative to the
+/// organized directory, when it is placed in its category
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Drop it, so every file lands directly at its destination
+    #[default]
+    Plain,
+    /// Recreate it below the destination folder (`--preserve-structure`)
+    Preserve,
+    /// Fold it into the file name with this separator (`--flatten-separator`)
+    Flatten(String),
+}
+
+impl Layout {
+    /// Apply the layout to a destination computed from the file name alone
+    pub fn apply(&self, destination: PathBuf, subdir: &Path) -> PathBuf {
+        let Some(file_name) = destination.file_name() else {
+            return destination;
+        };
+        if subdir.as_os_str().is_empty() {
+            return destination;
+        }
+        match self {
+            Layout::Plain => destination,
+            Layout::Preserve => {
+                let file_name = file_name.to_os_string();
+                let parent = destination.parent().unwrap_or(Path::new(""));
+                parent.join(subdir).join(file_name)
+            }
+            Layout::Flatten(separator) => {
+                let mut name = String::new();
+                for component in subdir.components() {
+                    name.push_str(&component.as_os_str().to_string_lossy());
+                    name.push_str(separator);
+                }
+                name.push_str(&file_name.to_string_lossy());
+                destination.with_file_name(name)
+            }
+        }
+    }
+}
+
+/// Placeholders a rename pattern may use
+pub const RENAME_PLACEHOLDERS: &[&str] = &["n", "timestamp", "hash"];
+
+/// Suffix put between the stem and the extension of a file renamed to avoid
+/// a conflict (`--rename-pattern`), such as `_{n}` for `notes_1.txt`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePattern {
+    suffix: String,
+}
+
+impl Default for RenamePattern {
+    fn default() -> Self {
+        Self {
+            suffix: "_{n}".to_string(),
+        }
+    }
+}
+
+impl RenamePattern {
+    pub fn parse(suffix: &str) -> Result<Self> {
+        let invalid = |message: String| {
+            OrganizerError::Template(format!("rename pattern '{}': {}", suffix, message))
+        };
+        if suffix.is_empty() {
+            return Err(invalid("cannot be empty".to_string()));
+        }
+        if suffix.contains(['/', '\\']) {
+            return Err(invalid("cannot contain a path separator".to_string()));
+        }
+        let mut rest = suffix;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| invalid("unclosed '{'".to_string()))?;
+            let name = &rest[start + 1..end];
+            if !RENAME_PLACEHOLDERS.contains(&name) {
+                return Err(invalid(format!(
+                    "unknown placeholder {{{}}} (expected one of: {})",
+                    name,
+                    RENAME_PLACEHOLDERS.join(", ")
+                )));
+            }
+            rest = &rest[end + 1..];
+        }
+        Ok(Self {
+            suffix: suffix.to_string(),
+        })
+    }
+
+    pub fn uses(&self, placeholder: &str) -> bool {
+        self.suffix.contains(&format!("{{{}}}", placeholder))
+    }
+
+    /// The suffix for attempt `n`, counting from 1. A pattern without `{n}`
+    /// gets `_N` added from the second attempt on.
+    pub fn render(&self, n: usize, timestamp: &str, hash: &str) -> String {
+        let suffix = self
+            .suffix
+            .replace("{n}", &n.to_string())
+            .replace("{timestamp}", timestamp)
+            .replace("{hash}", hash);
+        if n > 1 && !self.uses("n") {
+            format!("{}_{}", suffix, n)
+        } else {
+            suffix
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// `{key}`, or `{key:02}` to zero-pad numbers to a width
+    Placeholder {
+        key: String,
+        width: Option<usize>,
+    },
+}
+
+/// Destination path for a file relative to the organized directory, such as
+/// `{category}/{year}/{month}/{name}.{ext}`
+#[derive(Debug, Clone)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self> {
+        let invalid = |reason: &str| OrganizerError::Template(format!("'{}': {}", source, reason));
+
+        if source.trim().is_empty() {
+            return Err(invalid("template cannot be empty"));
+        }
+        if source.starts_with('/') || source.split('/').any(|segment| segment == "..") {
+            return Err(invalid(
+                "must be a relative path inside the organized directory",
+            ));
+        }
+        if source.ends_with('/') {
+            return Err(invalid("must end with a file name"));
+        }
+
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while !rest.is_empty() {
+            match rest.find(['{', '}']) {
+                Some(index) if rest[index..].starts_with('}') => {
+                    return Err(invalid("unmatched '}'"));
+                }
+                Some(index) => {
+                    if index > 0 {
+                        parts.push(Part::Literal(rest[..index].to_string()));
+                    }
+                    let end = rest[index..]
+                        .find('}')
+                        .ok_or_else(|| invalid("unmatched '{'"))?;
+                    let placeholder = &rest[index + 1..index + end];
+                    let (key, width) = match placeholder.split_once(':') {
+                        Some((key, spec)) => match spec.parse() {
+                            Ok(width) if spec.bytes().all(|b| b.is_ascii_digit()) => (key, Some(width)),
+                            _ => {
+                                return Err(invalid(&format!(
+                                    "invalid width '{}' in '{{{}}}' (expected digits, e.g. {{track:02}})",
+                                    spec, placeholder
+                                )))
+                            }
+                        },
+                        None => (placeholder, None),
+                    };
+                    if !PLACEHOLDERS.contains(&key) {
+                        return Err(invalid(&format!(
+                            "unknown placeholder '{{{}}}' (expected one of: {})",
+                            key,
+                            PLACEHOLDERS.join(", ")
+                        )));
+                    }
+                    parts.push(Part::Placeholder {
+                        key: key.to_string(),
+                        width,
+                    });
+                    rest = &rest[index + end + 1..];
+                }
+                None => {
+                    parts.push(Part::Literal(rest.to_string()));
+                    rest = "";
+                }
+            }
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Relative destination for `file`, with `{category}` filled in as `folder`
+    pub fn render(&self, file: &FileInfo, folder: &str) -> PathBuf {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => rendered.push_str(text),
+                Part::Placeholder { key, width } => match (value(key, file, folder), width) {
+                    // `{name}.{ext}` becomes just the name for files without an extension
+                    (Some(ext), _) if key == "ext" && ext.is_empty() => {
+                        if rendered.ends_with('.') {
+                            rendered.pop();
+                        }
+                    }
+                    (Some(value), Some(width)) if value.bytes().all(|b| b.is_ascii_digit()) => {
+                        rendered.push_str(&format!("{:0>width$}", value, width = width))
+                    }
+                    (Some(value), _) if !value.is_empty() => rendered.push_str(&sanitize(&value)),
+                    // A missing number such as an untagged track becomes zeros
+                    (None, Some(width)) => rendered.push_str(&"0".repeat(*width)),
+                    _ => rendered.push_str(UNKNOWN),
+                },
+            }
+        }
+        PathBuf::from(rendered)
+    }
+}
+
+fn value(key: &str, file: &FileInfo, folder: &str) -> Option<String> {
+    let os_str = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().to_string());
+    let tags = file.tags.as_ref();
+    match key {
+        "category" => Some(folder.to_string()),
+        "name" => os_str(file.path.file_stem()),
+        "ext" => Some(os_str(file.path.extension()).unwrap_or_default()),
+        "filename" => os_str(file.path.file_name()),
+        "year" => file.date.map(|d| format!("{:04}", d.year())),
+        "month" => file.date.map(|d| format!("{:02}", d.month())),
+        "day" => file.date.map(|d| format!("{:02}", d.day())),
+        "exif.year" => captured(file).map(|d| format!("{:04}", d.year())),
+        "exif.month" => captured(file).map(|d| format!("{:02}", d.month())),
+        "exif.day" => captured(file).map(|d| format!("{:02}", d.day())),
+        "exif.camera" => file.exif.as_ref().and_then(|e| e.camera.clone()),
+        "artist" => tags.and_then(|t| t.artist.clone().or_else(|| t.album_artist.clone())),
+        "album" => tags.and_then(|t| t.album.clone()),
+        // Untitled songs keep their file name so they don't all collide
+        "title" => tags
+            .and_then(|t| t.title.clone())
+            .or_else(|| os_str(file.path.file_stem())),
+        "track" => tags.and_then(|t| t.track).map(|n| n.to_string()),
+        _ => None,
+    }
+}
+
+fn captured(file: &FileInfo) -> Option<NaiveDateTime> {
+    file.exif.as_ref().and_then(|e| e.captured)
+}
+
+/// Make a placeholder value safe as a single path component on any platform:
+/// characters that are illegal on Windows or macOS become `_`, trailing dots
+/// and spaces are dropped, reserved device names get a suffix and overly long
+/// values are shortened.
+pub fn sanitize(value: &str) -> String {
+    const ILLEGAL: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let mut clean: String = value
+        .chars()
+        .map(|c| {
+            if ILLEGAL.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    if clean.len() > MAX_VALUE_LEN {
+        let mut end = MAX_VALUE_LEN;
+        while !clean.is_char_boundary(end) {
+            end -= 1;
+        }
+        clean.truncate(end);
+    }
+    let mut clean = clean.trim_start().trim_end_matches(['.', ' ']).to_string();
+
+    let stem_len = clean.find('.').unwrap_or(clean.len());
+    if RESERVED
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(&clean[..stem_len]))
+    {
+        clean.insert(stem_len, '_');
+    }
+    if clean.is_empty() {
+        return "_".to_string();
+    }
+    clean
+}
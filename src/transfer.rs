@@ -0,0 +1,241 @@
+use crate::hash::hash_file;
+use crate::journal::absolute;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, FileTimes, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Copy buffer size, which is also how often a copy reports progress
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// How a file is put into its category folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferMode {
+    /// Move the file, leaving nothing behind
+    #[default]
+    Move,
+    /// Copy the file, leaving the original in place
+    Copy,
+    /// Create a symbolic link to the original
+    Symlink,
+    /// Create a hard link to the original (same filesystem only)
+    Hardlink,
+}
+
+impl TransferMode {
+    /// Whether the original stays where it is
+    pub fn keeps_source(self) -> bool {
+        self != TransferMode::Move
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransferMode::Move => "move",
+            TransferMode::Copy => "copy",
+            TransferMode::Symlink => "symlink",
+            TransferMode::Hardlink => "hardlink",
+        }
+    }
+
+    /// Label for the count of files handled this way, as in "Moved: 3"
+    pub fn done_label(self) -> &'static str {
+        match self {
+            TransferMode::Move => "Moved",
+            TransferMode::Copy => "Copied",
+            TransferMode::Symlink | TransferMode::Hardlink => "Linked",
+        }
+    }
+}
+
+/// Put `source` at `destination` the way `mode` says. `progress` is called
+/// with the bytes copied so far whenever file contents are copied.
+pub fn transfer(
+    mode: TransferMode,
+    source: &Path,
+    destination: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<()> {
+    if fs::symlink_metadata(source)?.is_dir()
+        && matches!(mode, TransferMode::Copy | TransferMode::Hardlink)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is a directory, which can only be moved or symlinked",
+                source.display()
+            ),
+        ));
+    }
+    match mode {
+        TransferMode::Move => rename_or_copy(source, destination, progress),
+        TransferMode::Copy => copy_file(source, destination, progress),
+        TransferMode::Symlink => symlink(&absolute(source), destination),
+        TransferMode::Hardlink => match fs::hard_link(source, destination) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => Err(io::Error::new(
+                e.kind(),
+                "hard links cannot cross filesystems (use --mode symlink or copy)",
+            )),
+            result => result,
+        },
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Windows has separate kinds of links for files and directories
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> io::Result<()> {
+    if target.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Rename `source` to `destination`, falling back to a verified copy followed
+/// by removing the source when they are on different filesystems. A symbolic
+/// link is moved as a link, never as the file it points to.
+///
+/// `progress` is called with the bytes copied so far and the total size while
+/// a fallback copy runs; a plain rename never calls it.
+pub fn rename_or_copy(
+    source: &Path,
+    destination: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<()> {
+    if fs::symlink_metadata(source)?.file_type().is_symlink() {
+        return move_symlink(source, destination);
+    }
+    match fs::rename(source, destination) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices && source.is_dir() => {
+            Err(io::Error::new(
+                e.kind(),
+                format!(
+                    "{} is a directory and cannot be moved to another filesystem",
+                    source.display()
+                ),
+            ))
+        }
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_and_remove(source, destination, progress)
+        }
+        result => result,
+    }
+}
+
+/// Recreate the link `source` at `destination`, then remove the original. A
+/// relative target is made absolute so the moved link still resolves, and the
+/// link is recreated rather than renamed so this also works across filesystems.
+fn move_symlink(source: &Path, destination: &Path) -> io::Result<()> {
+    let target = fs::read_link(source)?;
+    let target = match source.parent() {
+        Some(parent) if target.is_relative() => absolute(&parent.join(target)),
+        _ => target,
+    };
+    symlink(&target, destination)?;
+    fs::remove_file(source)
+}
+
+/// Copy `source` to `destination` with [`copy_file`], then remove the source.
+/// A failed copy leaves the source untouched.
+pub(crate) fn copy_and_remove(
+    source: &Path,
+    destination: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<()> {
+    copy_file(source, destination, progress)?;
+    fs::remove_file(source)
+}
+
+/// Stream `source` into a temporary file next to `destination`, carry over its
+/// permissions and timestamps, check the copy's size and hash against the
+/// source, then move it into place. A failed copy is cleaned up.
+pub fn copy_file(
+    source: &Path,
+    destination: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<()> {
+    let partial = partial_path(destination)?;
+    if let Err(e) = copy_verified(source, &partial, progress) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&partial, destination) {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn copy_verified(
+    source: &Path,
+    partial: &Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> io::Result<()> {
+    let metadata = fs::metadata(source)?;
+    let total = metadata.len();
+    let mut reader = File::open(source)?;
+    let mut writer = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(partial)?;
+
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut copied = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        hasher.update(&buffer[..read]);
+        copied += read as u64;
+        progress(copied, total);
+    }
+    writer.sync_all()?;
+
+    let mut times = FileTimes::new().set_modified(metadata.modified()?);
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    writer.set_times(times)?;
+    writer.set_permissions(metadata.permissions())?;
+    drop(writer);
+
+    let written = fs::metadata(partial)?.len();
+    if copied != total || written != total {
+        return Err(io::Error::other(format!(
+            "copy of {} is incomplete ({} of {} bytes)",
+            source.display(),
+            written,
+            total
+        )));
+    }
+    if hash_file(partial)? != hasher.finalize() {
+        return Err(io::Error::other(format!(
+            "copy of {} does not match the original",
+            source.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Hidden temporary name in the destination directory, so an interrupted copy
+/// never looks like a finished file
+fn partial_path(destination: &Path) -> io::Result<PathBuf> {
+    let file_name = destination.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} has no file name", destination.display()),
+        )
+    })?;
+    Ok(destination.with_file_name(format!(".{}.organizer-part", file_name.to_string_lossy())))
+}
@@ -0,0 +1,226 @@
+use crate::cli::with_suffix;
+use crate::config::xdg_dir;
+use crate::error::{OrganizerError, Result};
+use crate::journal::absolute;
+use crate::transfer::rename_or_copy;
+use chrono::Local;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// The user's trash as described by the freedesktop.org trash specification.
+/// Files on the filesystem of the home trash go there; files on other
+/// filesystems go to the trash directory at the top of their mount point, so
+/// trashing them never copies data across filesystems.
+#[derive(Debug, Clone)]
+pub struct Trash {
+    /// `None` where the desktop keeps its own trash, which would never show
+    /// files put in a freedesktop.org one
+    home: Option<PathBuf>,
+}
+
+/// Whether the desktop follows the freedesktop.org trash specification
+const FREEDESKTOP: bool = cfg!(all(
+    unix,
+    not(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "android"
+    ))
+));
+
+impl Trash {
+    /// `$XDG_DATA_HOME/Trash`, falling back to `~/.local/share/Trash`. Other
+    /// platforms get a trash that refuses every file, so replacing or removing
+    /// one there needs `--no-trash`.
+    pub fn home() -> Result<Self> {
+        if !FREEDESKTOP {
+            return Ok(Self { home: None });
+        }
+        xdg_dir("XDG_DATA_HOME", ".local/share")
+            .map(|dir| Self::at(dir.join("Trash")))
+            .ok_or_else(|| {
+                OrganizerError::Trash(
+                    "cannot determine the home directory (use --no-trash to delete files instead)"
+                        .to_string(),
+                )
+            })
+    }
+
+    /// A trash whose home trash directory is `dir`, created on first use
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            home: Some(dir.into()),
+        }
+    }
+
+    /// Move `path` to the trash along with a `.trashinfo` file recording where
+    /// it came from and when, and return where it ended up
+    pub fn put(&self, path: &Path) -> Result<PathBuf> {
+        let home = self.home.as_deref().ok_or_else(|| {
+            OrganizerError::Trash(format!(
+                "cannot move {} to the trash: this platform has no freedesktop.org trash (use --no-trash to delete files instead)",
+                path.display()
+            ))
+        })?;
+        let path = absolute(path);
+        let name = path.file_name().ok_or_else(|| {
+            OrganizerError::InvalidPath(format!("cannot trash {}", path.display()))
+        })?;
+        let (trash_dir, original) = trash_dir_for(home, &path)?;
+        let files = trash_dir.join("files");
+        fs::create_dir_all(&files)?;
+        fs::create_dir_all(trash_dir.join("info"))?;
+
+        // The info file is created first and exclusively, which reserves the
+        // name against other programs trashing a file of the same name. The
+        // counter has no limit, as the trash keeps every file ever put there.
+        let first = files.join(name);
+        let occupied = |p: &Path| fs::symlink_metadata(p).is_ok() || info_path(p).exists();
+        let mut attempt: u64 = 0;
+        let (trashed, mut info) = loop {
+            let trashed = match attempt {
+                0 => first.clone(),
+                n => with_suffix(&first, &format!("_{}", n)),
+            };
+            attempt += 1;
+            if occupied(&trashed) {
+                continue;
+            }
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(info_path(&trashed))
+            {
+                Ok(info) => break (trashed, info),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        let contents = format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            percent_encode(&original),
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        );
+        let moved = info
+            .write_all(contents.as_bytes())
+            .and_then(|_| rename_or_copy(&path, &trashed, &mut |_, _| {}));
+        if let Err(e) = moved {
+            let _ = fs::remove_file(info_path(&trashed));
+            return Err(OrganizerError::Trash(format!(
+                "cannot move {} to the trash: {}",
+                path.display(),
+                e
+            )));
+        }
+        Ok(trashed)
+    }
+}
+
+/// Trash directory for `path` given the home trash `home`, and the path to
+/// record in its info file
+#[cfg(unix)]
+fn trash_dir_for(home: &Path, path: &Path) -> Result<(PathBuf, PathBuf)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = fs::symlink_metadata(path)?.dev();
+    let home_device = home
+        .ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .map(|metadata| metadata.dev());
+    if home_device == Some(device) {
+        return Ok((home.to_path_buf(), path.to_path_buf()));
+    }
+
+    // The top of the mount point is the highest ancestor on the same device
+    let top = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| fs::metadata(dir).is_ok_and(|m| m.dev() == device))
+        .last()
+        .unwrap_or(Path::new("/"));
+    let original = path.strip_prefix(top).unwrap_or(path).to_path_buf();
+    match mount_trash_dir(top) {
+        Some(dir) => Ok((dir, original)),
+        // Without a usable trash on that filesystem, the home trash takes a copy
+        None => Ok((home.to_path_buf(), path.to_path_buf())),
+    }
+}
+
+#[cfg(not(unix))]
+fn trash_dir_for(home: &Path, path: &Path) -> Result<(PathBuf, PathBuf)> {
+    Ok((home.to_path_buf(), path.to_path_buf()))
+}
+
+/// Put a trashed file back at `original` and drop its info file
+pub fn restore(trashed: &Path, original: &Path) -> io::Result<()> {
+    if let Some(parent) = original.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    rename_or_copy(trashed, original, &mut |_, _| {})?;
+    let _ = fs::remove_file(info_path(trashed));
+    Ok(())
+}
+
+/// `$top/.Trash/$uid` when the administrator set up a shared `.Trash` (a real,
+/// sticky directory), otherwise `$top/.Trash-$uid`, created if needed
+#[cfg(unix)]
+fn mount_trash_dir(top: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    // SAFETY: getuid cannot fail and has no preconditions
+    let uid = unsafe { libc::getuid() };
+    let shared = top.join(".Trash");
+    if let Ok(metadata) = fs::symlink_metadata(&shared) {
+        if metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0 {
+            let dir = shared.join(uid.to_string());
+            if fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(&dir)
+                .is_ok()
+            {
+                return Some(dir);
+            }
+        }
+    }
+
+    let own = top.join(format!(".Trash-{}", uid));
+    let _ = fs::DirBuilder::new().mode(0o700).create(&own);
+    fs::symlink_metadata(&own)
+        .is_ok_and(|m| m.is_dir() && m.uid() == uid)
+        .then_some(own)
+}
+
+/// `info/<name>.trashinfo` for a file at `files/<name>`
+fn info_path(trashed: &Path) -> PathBuf {
+    let trash_dir = trashed
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""));
+    let mut name = trashed.file_name().unwrap_or_default().to_os_string();
+    name.push(".trashinfo");
+    trash_dir.join("info").join(name)
+}
+
+/// Escape a path the way URLs are, as the `Path=` key requires
+fn percent_encode(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+    let mut encoded = String::with_capacity(bytes.len());
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
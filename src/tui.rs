@@ -1,4 +1,7 @@
-use crate::cli::{FileInfo, OrganizeStats};
+use crate::cli::{truncate_str, FileInfo, OrganizeStats};
+use crate::conflict::{Choice, Clash, ASK_CHOICES};
+use crate::filter::FilteredCounts;
+use crate::transfer::TransferMode;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
@@ -9,9 +12,7 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{
-        Block, Borders, Gauge, List, ListItem, Padding, Paragraph, Wrap,
-    },
+    widgets::{Block, Borders, Clear, Gauge, List, ListItem, Padding, Paragraph, Wrap},
     Frame, Terminal,
 };
 use std::{
@@ -27,17 +28,13 @@ pub struct PreviewApp {
     pub selected_category: Option<usize>,
     pub scroll_offset: usize,
     pub categories: Vec<String>,
+    /// Files the scan left out because of filters
+    pub filtered: FilteredCounts,
 }
 
 impl PreviewApp {
-    pub fn new(files: Vec<FileInfo>) -> Self {
+    pub fn new(files: Vec<FileInfo>, categories: Vec<String>) -> Self {
         let total_size = files.iter().map(|f| f.size).sum();
-        let categories = vec![
-            "Multimedia".to_string(),
-            "Docs".to_string(),
-            "Compressed".to_string(),
-            "Misc".to_string(),
-        ];
         Self {
             files,
             total_size,
@@ -45,6 +42,7 @@ impl PreviewApp {
             selected_category: None,
             scroll_offset: 0,
             categories,
+            filtered: FilteredCounts::default(),
         }
     }
 
@@ -105,10 +103,8 @@ impl PreviewApp {
                             // If last_was_esc_back is true, ignore this ESC (key repeat/held)
                             last_was_esc_back = false;
                         }
-                        KeyCode::Enter => {
-                            if self.selected_category.is_none() {
-                                break; // Proceed to organize
-                            }
+                        KeyCode::Enter if self.selected_category.is_none() => {
+                            break; // Proceed to organize
                         }
                         KeyCode::Left | KeyCode::Up => {
                             if self.selected_category.is_none() {
@@ -126,13 +122,13 @@ impl PreviewApp {
                                 self.scroll_offset = self.scroll_offset.saturating_add(1);
                             }
                         }
-                        KeyCode::Char(c) if c.is_ascii_digit() => {
-                            if self.selected_category.is_none() {
-                                let digit = c.to_digit(10).unwrap() as usize;
-                                if digit > 0 && digit <= self.categories.len() {
-                                    self.selected_category = Some(digit - 1);
-                                    self.scroll_offset = 0;
-                                }
+                        KeyCode::Char(c)
+                            if c.is_ascii_digit() && self.selected_category.is_none() =>
+                        {
+                            let digit = c.to_digit(10).unwrap() as usize;
+                            if digit > 0 && digit <= self.categories.len() {
+                                self.selected_category = Some(digit - 1);
+                                self.scroll_offset = 0;
                             }
                         }
                         _ => {}
@@ -170,26 +166,36 @@ impl PreviewApp {
     }
 
     fn render_header(&self, f: &mut Frame, area: Rect) {
-        let title = Paragraph::new(vec![
-            Line::from(vec![
-                Span::styled(
-                    "File Organizer v0.2.0",
-                    Style::default()
-                        .fg(Color::Cyan)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("  |  "),
-                Span::styled(
-                    format!("{} files", self.files.len()),
-                    Style::default().fg(Color::Yellow),
-                ),
-                Span::raw("  |  "),
-                Span::styled(
-                    format_size(self.total_size),
-                    Style::default().fg(Color::Green),
+        let mut spans = vec![
+            Span::styled(
+                "File Organizer v0.2.0",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("  |  "),
+            Span::styled(
+                format!("{} files", self.files.len()),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw("  |  "),
+            Span::styled(
+                format_size(self.total_size),
+                Style::default().fg(Color::Green),
+            ),
+        ];
+        if self.filtered.total() > 0 {
+            spans.push(Span::raw("  |  "));
+            spans.push(Span::styled(
+                format!(
+                    "{} filtered out ({})",
+                    self.filtered.total(),
+                    self.filtered.describe()
                 ),
-            ]),
-        ])
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        let title = Paragraph::new(vec![Line::from(spans)])
         .alignment(Alignment::Center)
         .block(
             Block::default()
@@ -208,40 +214,29 @@ impl PreviewApp {
         for file in &self.files {
             categories
                 .entry(file.category.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(file);
         }
 
-        // Create grid layout (2 rows, 2 columns for 4 categories)
+        // Create grid layout (2 columns, as many rows as the categories need)
+        let row_count = self.categories.len().div_ceil(2).max(1);
         let rows = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .constraints(vec![Constraint::Ratio(1, row_count as u32); row_count])
             .split(area);
 
-        let top_cols = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
-            ])
-            .split(rows[0]);
-
-        let bottom_cols = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(50),
-                Constraint::Percentage(50),
-            ])
-            .split(rows[1]);
+        let mut areas = Vec::with_capacity(row_count * 2);
+        for row in rows.iter() {
+            let cols = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(*row);
+            areas.extend(cols.iter().copied());
+        }
 
         // Render each category
-        let category_names = vec!["Multimedia", "Docs", "Compressed", "Misc"];
-        let areas = vec![top_cols[0], top_cols[1], bottom_cols[0], bottom_cols[1]];
-
-        for (idx, cat_name) in category_names.iter().enumerate() {
-            if idx < areas.len() {
-                self.render_category_box(f, areas[idx], cat_name, idx, categories.get(*cat_name));
-            }
+        for (idx, cat_name) in self.categories.iter().enumerate() {
+            self.render_category_box(f, areas[idx], cat_name, idx, categories.get(cat_name));
         }
     }
 
@@ -265,35 +260,25 @@ impl PreviewApp {
             .collect();
 
         let total_size: u64 = category_files.iter().map(|f| f.size).sum();
+        // Directories moved whole get a column with the number of files inside
+        let has_dirs = category_files.iter().any(|f| f.contents.is_some());
 
         // Header
-        let color = match category_name.as_str() {
-            "Multimedia" => Color::Magenta,
-            "Docs" => Color::Blue,
-            "Compressed" => Color::Yellow,
-            _ => Color::White,
-        };
+        let color = category_color(category_idx);
 
-        let header = Paragraph::new(vec![
-            Line::from(vec![
-                Span::styled(
-                    format!("{} Category", category_name),
-                    Style::default()
-                        .fg(color)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("  |  "),
-                Span::styled(
-                    format!("{} files", category_files.len()),
-                    Style::default().fg(Color::Yellow),
-                ),
-                Span::raw("  |  "),
-                Span::styled(
-                    format_size(total_size),
-                    Style::default().fg(Color::Green),
-                ),
-            ]),
-        ])
+        let header = Paragraph::new(vec![Line::from(vec![
+            Span::styled(
+                format!("{} Category", category_name),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw("  |  "),
+            Span::styled(
+                count_label(&category_files),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw("  |  "),
+            Span::styled(format_size(total_size), Style::default().fg(Color::Green)),
+        ])])
         .alignment(Alignment::Center)
         .block(
             Block::default()
@@ -313,26 +298,44 @@ impl PreviewApp {
             .skip(visible_start)
             .take(available_height)
             .map(|file| {
-                let filename = file
+                let mut filename = file
                     .path
                     .file_name()
                     .unwrap_or_default()
-                    .to_string_lossy();
+                    .to_string_lossy()
+                    .into_owned();
+                if file.contents.is_some() {
+                    filename.push('/');
+                }
 
-                // Calculate max filename width: width - padding (4) - size (12) - borders (2) - spacing (2)
-                let max_filename_width = chunks[1].width.saturating_sub(20) as usize;
+                // Calculate max filename width: width - padding (4) - size (12) - borders (2) - spacing (2),
+                // and the file count column (13) when there is one
+                let count_width = if has_dirs { 13 } else { 0 };
+                let max_filename_width = chunks[1].width.saturating_sub(20 + count_width) as usize;
                 let truncated = truncate_str(&filename, max_filename_width);
 
                 // Pad filename to fixed width for alignment
                 let padded_filename = format!("{:<width$}", truncated, width = max_filename_width);
                 let size_str = format!("{:>12}", format_size(file.size));
 
-                ListItem::new(Line::from(vec![
+                let mut spans = vec![
                     Span::raw("  "),
                     Span::styled(padded_filename, Style::default().fg(Color::White)),
-                    Span::raw(" "),
-                    Span::styled(size_str, Style::default().fg(Color::Yellow)),
-                ]))
+                ];
+                if has_dirs {
+                    let count_str = match &file.contents {
+                        Some(contents) => format!("{} files", contents.files),
+                        None => String::new(),
+                    };
+                    spans.push(Span::styled(
+                        format!(" {:>12}", count_str),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(size_str, Style::default().fg(Color::Yellow)));
+
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
@@ -377,13 +380,12 @@ impl PreviewApp {
             .map(|f| f.iter().map(|fi| fi.size).sum())
             .unwrap_or(0);
 
-        let title = format!(" [{}] {} ({}) ", cat_idx + 1, name, count);
-        let color = match name {
-            "Multimedia" => Color::Magenta,
-            "Docs" => Color::Blue,
-            "Compressed" => Color::Yellow,
-            _ => Color::White,
+        let title = if cat_idx < 9 {
+            format!(" [{}] {} ({}) ", cat_idx + 1, name, count)
+        } else {
+            format!(" {} ({}) ", name, count)
         };
+        let color = category_color(cat_idx);
 
         let mut items: Vec<ListItem> = vec![
             ListItem::new(Line::from(vec![
@@ -406,11 +408,15 @@ impl PreviewApp {
             };
 
             for file in file_list.iter().take(max_files) {
-                let filename = file
+                let mut filename = file
                     .path
                     .file_name()
                     .unwrap_or_default()
-                    .to_string_lossy();
+                    .to_string_lossy()
+                    .into_owned();
+                if file.contents.is_some() {
+                    filename.push('/');
+                }
 
                 // Calculate max filename width: area width - bullet (2) - size (10) - padding (4) - borders (2)
                 let max_filename_width = area.width.saturating_sub(18) as usize;
@@ -448,8 +454,14 @@ impl PreviewApp {
     }
 
     fn render_footer(&self, f: &mut Frame, area: Rect) {
+        let keys = format!("[1-{}] ", self.categories.len().clamp(1, 9));
         let footer = Paragraph::new(Line::from(vec![
-            Span::styled("[1-4] ", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                keys,
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
             Span::raw("View Category  "),
             Span::styled("[Enter] ", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
             Span::raw("Organize  "),
@@ -469,12 +481,18 @@ impl PreviewApp {
 
 pub struct ProgressApp {
     pub total_files: usize,
+    pub categories: Vec<String>,
     pub current_file: String,
     pub current_category: String,
     pub current_mime: String,
     pub current_size: u64,
+    /// Bytes copied and total size while the current file is being copied
+    pub copy_progress: Option<(u64, u64)>,
+    pub mode: TransferMode,
     pub stats: OrganizeStats,
     pub category_progress: HashMap<String, CategoryProgress>,
+    /// Conflict waiting for an answer under `--conflict ask`
+    pub clash: Option<Clash>,
 }
 
 #[derive(Clone)]
@@ -484,29 +502,58 @@ pub struct CategoryProgress {
 }
 
 impl ProgressApp {
-    pub fn new(total_files: usize) -> Self {
+    pub fn new(total_files: usize, categories: Vec<String>) -> Self {
         let mut category_progress = HashMap::new();
-        for cat in ["Multimedia", "Docs", "Compressed", "Misc"] {
-            category_progress.insert(
-                cat.to_string(),
-                CategoryProgress {
-                    count: 0,
-                    size: 0,
-                },
-            );
+        for cat in &categories {
+            category_progress.insert(cat.clone(), CategoryProgress { count: 0, size: 0 });
         }
 
         Self {
             total_files,
+            categories,
             current_file: String::new(),
             current_category: String::new(),
             current_mime: String::new(),
             current_size: 0,
+            copy_progress: None,
+            mode: TransferMode::Move,
             stats: OrganizeStats::new(),
             category_progress,
+            clash: None,
         }
     }
 
+    /// Show a dialog with both files of `clash` over the progress view and
+    /// wait for a choice. Esc skips this one file.
+    pub fn ask(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        clash: &Clash,
+    ) -> io::Result<Choice> {
+        self.clash = Some(clash.clone());
+        let choice = loop {
+            terminal.draw(|f| self.render(f))?;
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        if let Some(choice) = Choice::from_key(c) {
+                            break choice;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        break Choice {
+                            strategy: "skip",
+                            apply_to_all: false,
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        };
+        self.clash = None;
+        Ok(choice)
+    }
+
     pub fn update_current(&mut self, file: &FileInfo) {
         self.current_file = file
             .path
@@ -515,8 +562,16 @@ impl ProgressApp {
             .to_string_lossy()
             .to_string();
         self.current_category = file.category.clone();
-        self.current_mime = file.mime_type.clone().unwrap_or_else(|| "unknown".to_string());
+        self.current_mime = match &file.mime_type {
+            Some(mime) => format!("{} ({})", mime, file.detected_by),
+            None => "unknown".to_string(),
+        };
         self.current_size = file.size;
+        self.copy_progress = None;
+    }
+
+    pub fn update_copy(&mut self, copied: u64, total: u64) {
+        self.copy_progress = Some((copied, total));
     }
 
     pub fn update_category(&mut self, category: &str, size: u64) {
@@ -552,17 +607,95 @@ impl ProgressApp {
 
         // Summary
         self.render_summary(f, chunks[4]);
+
+        if let Some(clash) = &self.clash {
+            self.render_clash(f, clash);
+        }
     }
 
-    fn render_title(&self, f: &mut Frame, area: Rect) {
-        let title = Paragraph::new(Line::from(vec![
-            Span::styled(
-                "Organizing Files",
+    fn render_clash(&self, f: &mut Frame, clash: &Clash) {
+        let area = f.area();
+        let width = area.width.saturating_sub(8).min(90);
+        let height = 13.min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + (area.height - height) / 2,
+            width,
+            height,
+        };
+
+        let label = Style::default().fg(Color::DarkGray);
+        let side = |name: &'static str, details: String| {
+            Line::from(vec![Span::styled(name, label), Span::raw(details)])
+        };
+        let mut lines = vec![
+            Line::from(Span::styled(
+                truncate_str(
+                    &clash.destination.path.display().to_string(),
+                    (width as usize).saturating_sub(4),
+                ),
                 Style::default()
                     .fg(Color::Cyan)
                     .add_modifier(Modifier::BOLD),
-            ),
-        ]))
+            )),
+            Line::from("already exists"),
+            Line::from(""),
+            side("Existing: ", clash.destination.describe()),
+            side("Incoming: ", clash.source.describe()),
+            Line::from(""),
+        ];
+        if clash.identical() {
+            lines.insert(
+                5,
+                Line::from(Span::styled(
+                    "The files are identical",
+                    Style::default().fg(Color::Green),
+                )),
+            );
+        }
+
+        let mut keys = Vec::new();
+        for (key, strategy) in ASK_CHOICES {
+            keys.push(Span::styled(
+                format!("[{}]", key),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            keys.push(Span::raw(format!("{}  ", &strategy[1..])));
+        }
+        lines.push(Line::from(keys));
+        lines.push(Line::from(Span::styled(
+            "Shift+key: apply to all remaining conflicts  |  Esc: skip this file",
+            label,
+        )));
+
+        let dialog = Paragraph::new(lines)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" File already exists ")
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .padding(Padding::new(1, 1, 1, 0)),
+            );
+        f.render_widget(Clear, popup);
+        f.render_widget(dialog, popup);
+    }
+
+    fn render_title(&self, f: &mut Frame, area: Rect) {
+        let heading = match self.mode {
+            TransferMode::Move => "Organizing Files",
+            TransferMode::Copy => "Copying Files",
+            TransferMode::Symlink | TransferMode::Hardlink => "Linking Files",
+        };
+        let title = Paragraph::new(Line::from(vec![Span::styled(
+            heading,
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )]))
         .alignment(Alignment::Center)
         .block(
             Block::default()
@@ -574,7 +707,8 @@ impl ProgressApp {
     }
 
     fn render_overall_progress(&self, f: &mut Frame, area: Rect) {
-        let processed = self.stats.moved + self.stats.skipped + self.stats.errors;
+        let processed =
+            self.stats.placed() + self.stats.skipped + self.stats.deduplicated + self.stats.errors;
         let ratio = if self.total_files > 0 {
             processed as f64 / self.total_files as f64
         } else {
@@ -598,12 +732,11 @@ impl ProgressApp {
     }
 
     fn render_category_status(&self, f: &mut Frame, area: Rect) {
-        let categories = vec!["Multimedia", "Docs", "Compressed", "Misc"];
-
-        let items: Vec<ListItem> = categories
+        let items: Vec<ListItem> = self
+            .categories
             .iter()
             .map(|cat| {
-                let prog = self.category_progress.get(*cat).unwrap();
+                let prog = &self.category_progress[cat];
                 let is_current = *cat == self.current_category;
 
                 let (icon, style) = if prog.count > 0 {
@@ -653,8 +786,23 @@ impl ProgressApp {
     }
 
     fn render_current_file(&self, f: &mut Frame, area: Rect) {
+        let status = match self.copy_progress {
+            Some((copied, total)) => Line::from(vec![
+                Span::raw("  Copying: "),
+                Span::styled(
+                    format!(
+                        "{}% ({} of {})",
+                        copied * 100 / total.max(1),
+                        format_size(copied),
+                        format_size(total)
+                    ),
+                    Style::default().fg(Color::Yellow),
+                ),
+            ]),
+            None => Line::from(""),
+        };
         let text = vec![
-            Line::from(""),
+            status,
             Line::from(vec![
                 Span::raw("  Processing: "),
                 Span::styled(
@@ -690,11 +838,18 @@ impl ProgressApp {
     fn render_summary(&self, f: &mut Frame, area: Rect) {
         let summary = Paragraph::new(Line::from(vec![
             Span::styled("✓ ", Style::default().fg(Color::Green)),
-            Span::raw(format!("Moved: {} ", self.stats.moved)),
+            Span::raw(format!(
+                "{}: {} ",
+                self.mode.done_label(),
+                self.stats.placed()
+            )),
             Span::raw("  "),
             Span::styled("⊘ ", Style::default().fg(Color::Yellow)),
             Span::raw(format!("Skipped: {} ", self.stats.skipped)),
             Span::raw("  "),
+            Span::styled("≡ ", Style::default().fg(Color::Blue)),
+            Span::raw(format!("Duplicates: {} ", self.stats.deduplicated)),
+            Span::raw("  "),
             Span::styled("✗ ", Style::default().fg(Color::Red)),
             Span::raw(format!("Errors: {}", self.stats.errors)),
         ]))
@@ -710,6 +865,21 @@ impl ProgressApp {
     }
 }
 
+/// Colour for the category at `idx`, so it stays the same across screens
+fn category_color(idx: usize) -> Color {
+    const PALETTE: [Color; 8] = [
+        Color::Magenta,
+        Color::Blue,
+        Color::Yellow,
+        Color::White,
+        Color::Green,
+        Color::Cyan,
+        Color::LightRed,
+        Color::LightMagenta,
+    ];
+    PALETTE[idx % PALETTE.len()]
+}
+
 fn format_size(size: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -726,33 +896,41 @@ fn format_size(size: u64) -> String {
     }
 }
 
-fn truncate_str(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+/// "3 files", "2 folders" or "3 files, 2 folders" for the entries of a category
+fn count_label(files: &[&FileInfo]) -> String {
+    let folders = files.iter().filter(|f| f.contents.is_some()).count();
+    match (files.len() - folders, folders) {
+        (loose, 0) => format!("{} files", loose),
+        (0, folders) => format!("{} folders", folders),
+        (loose, folders) => format!("{} files, {} folders", loose, folders),
     }
 }
 
 pub struct SummaryApp {
     pub stats: OrganizeStats,
     pub category_progress: HashMap<String, CategoryProgress>,
+    pub categories: Vec<String>,
     pub elapsed_time: Duration,
     pub total_size_moved: u64,
+    pub mode: TransferMode,
 }
 
 impl SummaryApp {
     pub fn new(
         stats: OrganizeStats,
         category_progress: HashMap<String, CategoryProgress>,
+        categories: Vec<String>,
         elapsed_time: Duration,
         total_size_moved: u64,
+        mode: TransferMode,
     ) -> Self {
         Self {
             stats,
             category_progress,
+            categories,
             elapsed_time,
             total_size_moved,
+            mode,
         }
     }
 
@@ -802,7 +980,7 @@ impl SummaryApp {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
-                Constraint::Length(8),
+                Constraint::Length(10),
                 Constraint::Min(10),
                 Constraint::Length(3),
             ])
@@ -823,14 +1001,14 @@ impl SummaryApp {
 
     fn render_title(&self, f: &mut Frame, area: Rect) {
         let success_rate = if self.stats.total_files > 0 {
-            (self.stats.moved as f64 / self.stats.total_files as f64 * 100.0) as u8
+            (self.stats.placed() as f64 / self.stats.total_files as f64 * 100.0) as u8
         } else {
             0
         };
 
         let (title, color) = if self.stats.errors > 0 {
             ("Organization Completed with Errors", Color::Yellow)
-        } else if self.stats.moved == self.stats.total_files {
+        } else if self.stats.placed() + self.stats.deduplicated == self.stats.total_files {
             ("Organization Completed Successfully!", Color::Green)
         } else {
             ("Organization Completed", Color::Cyan)
@@ -869,12 +1047,12 @@ impl SummaryApp {
             0.0
         };
         let files_per_sec = if elapsed_secs > 0.0 {
-            self.stats.moved as f64 / elapsed_secs
+            self.stats.placed() as f64 / elapsed_secs
         } else {
             0.0
         };
 
-        let items = vec![
+        let mut items = vec![
             ListItem::new(Line::from(vec![
                 Span::styled("Total Files:    ", Style::default().fg(Color::DarkGray)),
                 Span::styled(
@@ -883,9 +1061,12 @@ impl SummaryApp {
                 ),
             ])),
             ListItem::new(Line::from(vec![
-                Span::styled("✓ Moved:        ", Style::default().fg(Color::Green)),
                 Span::styled(
-                    format!("{}", self.stats.moved),
+                    format!("✓ {:<14}", format!("{}:", self.mode.done_label())),
+                    Style::default().fg(Color::Green),
+                ),
+                Span::styled(
+                    format!("{}", self.stats.placed()),
                     Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
                 ),
                 Span::raw("  "),
@@ -901,6 +1082,15 @@ impl SummaryApp {
                     Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
                 ),
             ])),
+            ListItem::new(Line::from(vec![
+                Span::styled("≡ Duplicates:   ", Style::default().fg(Color::Blue)),
+                Span::styled(
+                    format!("{}", self.stats.deduplicated),
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ])),
             ListItem::new(Line::from(vec![
                 Span::styled("✗ Errors:       ", Style::default().fg(Color::Red)),
                 Span::styled(
@@ -924,6 +1114,21 @@ impl SummaryApp {
                 ),
             ])),
         ];
+        if self.stats.pruned > 0 {
+            // Right after the duplicates line
+            items.insert(
+                5,
+                ListItem::new(Line::from(vec![
+                    Span::styled("✂ Empty dirs:   ", Style::default().fg(Color::Blue)),
+                    Span::styled(
+                        format!("{} removed", self.stats.pruned),
+                        Style::default()
+                            .fg(Color::Blue)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                ])),
+            );
+        }
 
         let list = List::new(items).block(
             Block::default()
@@ -937,12 +1142,11 @@ impl SummaryApp {
     }
 
     fn render_category_breakdown(&self, f: &mut Frame, area: Rect) {
-        let categories = vec!["Multimedia", "Docs", "Compressed", "Misc"];
-
-        let items: Vec<ListItem> = categories
+        let items: Vec<ListItem> = self
+            .categories
             .iter()
             .filter_map(|cat| {
-                self.category_progress.get(*cat).and_then(|prog| {
+                self.category_progress.get(cat).and_then(|prog| {
                     if prog.count > 0 {
                         Some(ListItem::new(Line::from(vec![
                             Span::styled(
@@ -0,0 +1,274 @@
+use crate::categories::CategoryRegistry;
+use crate::cli::{conflict_strategy, file_info, move_file, relative, MoveOutcome, MyOrganizer};
+use crate::conflict::{prompt_terminal, Asker};
+use crate::duplicates::DUPLICATES_FOLDER;
+use crate::error::{OrganizerError, Result};
+use crate::filter::{IgnoreStack, ScanFilter, IGNORE_FILE};
+use crate::journal::Journal;
+use crate::projects::{inside_project, ProjectMode};
+use crate::scan::{scan_filter, FollowSymlinks};
+use colored::Colorize;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+/// How often pending files are checked for having settled
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Suffixes browsers and download managers use while a file is still being written
+const PARTIAL_SUFFIXES: &[&str] = &[
+    ".part",
+    ".partial",
+    ".crdownload",
+    ".download",
+    ".opdownload",
+    ".tmp",
+    ".!qb",
+];
+
+/// A file seen by the watcher that has not settled yet
+struct Pending {
+    last_change: Instant,
+    size: Option<u64>,
+}
+
+/// Watch `base_path` and organize files as they arrive into category folders
+/// under `output`, until interrupted
+pub fn watch(
+    base_path: &Path,
+    output: &Path,
+    registry: &CategoryRegistry,
+    journal: &mut Journal,
+    args: &MyOrganizer,
+) -> Result<()> {
+    let mut filter = scan_filter(args)?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(notify_error)?;
+    let mode = if args.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(base_path, mode).map_err(notify_error)?;
+
+    println!(
+        "{} {} {}",
+        "Watching".bright_cyan().bold(),
+        base_path.display().to_string().bright_yellow(),
+        "(Ctrl+C to stop)".bright_black()
+    );
+
+    let settle = Duration::from_secs(args.settle);
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    let mut asker = Asker::default();
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                for path in changed_paths(&event) {
+                    if is_candidate(&path, base_path, registry, &filter, args.projects) {
+                        pending.insert(
+                            path,
+                            Pending {
+                                last_change: Instant::now(),
+                                size: None,
+                            },
+                        );
+                    }
+                }
+            }
+            Ok(Err(e)) => {
+                eprintln!("  {} {}", "✗".bright_red(), e.to_string().bright_red());
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let settled = take_settled(&mut pending, settle);
+        filter.refresh_now();
+        for path in settled {
+            // Size and age are only known once the file has settled
+            if fs::metadata(&path).is_ok_and(|metadata| filter.check_limits(&metadata).is_some()) {
+                continue;
+            }
+            organize_arrival(path, base_path, output, registry, journal, args, &mut asker);
+        }
+    }
+
+    Ok(())
+}
+
+/// Paths that may have gained new content from this event
+fn changed_paths(event: &Event) -> Vec<PathBuf> {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Modify(_) => event.paths.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a file name marks a download that is still in progress
+pub fn is_partial_download(path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+    PARTIAL_SUFFIXES
+        .iter()
+        .any(|suffix| file_name.ends_with(suffix))
+}
+
+/// Whether a path is a finished download we should organize
+pub fn is_candidate(
+    path: &Path,
+    base_path: &Path,
+    registry: &CategoryRegistry,
+    filter: &ScanFilter,
+    projects: ProjectMode,
+) -> bool {
+    if path.file_name().is_none_or(|name| name == IGNORE_FILE) || is_partial_download(path) {
+        return false;
+    }
+
+    // Ignore anything inside the category folders we move files into, and
+    // the extra copies `organizer duplicates` set aside
+    let relative = path.strip_prefix(base_path).unwrap_or(path);
+    let mut dirs = relative.parent().into_iter().flat_map(|p| p.components());
+    if dirs.any(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        registry.is_category_folder(&name) || name == DUPLICATES_FOLDER
+    }) {
+        return false;
+    }
+
+    // Leave alone whatever a scan would have filtered out, including files
+    // written inside a project
+    filter.allows_path(relative)
+        && (projects == ProjectMode::Scan || !inside_project(base_path, path))
+        && IgnoreStack::for_path(base_path, path)
+            .is_ok_and(|ignores| !ignores.is_ignored(path, false))
+}
+
+/// Remove and return the files whose size has not changed for the settle period
+fn take_settled(pending: &mut HashMap<PathBuf, Pending>, settle: Duration) -> Vec<PathBuf> {
+    let mut settled = Vec::new();
+
+    pending.retain(|path, entry| {
+        if entry.last_change.elapsed() < settle {
+            return true;
+        }
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            // Gone or not a regular file (e.g. a new directory): stop tracking it
+            _ => return false,
+        };
+        if entry.size != Some(metadata.len()) {
+            // Still growing, or never measured: wait another settle period
+            entry.size = Some(metadata.len());
+            entry.last_change = Instant::now();
+            return true;
+        }
+        settled.push(path.clone());
+        false
+    });
+
+    settled
+}
+
+fn organize_arrival(
+    path: PathBuf,
+    base_path: &Path,
+    output: &Path,
+    registry: &CategoryRegistry,
+    journal: &mut Journal,
+    args: &MyOrganizer,
+    asker: &mut Asker,
+) {
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(_) => return,
+    };
+    let subdir = path
+        .strip_prefix(base_path)
+        .ok()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    // Like a scan, organize the file a new link points to only when following
+    // links, and only when it is inside the watched directory
+    let inside =
+        |target: &Path| fs::canonicalize(base_path).is_ok_and(|base| target.starts_with(base));
+    let path = match fs::canonicalize(&path) {
+        Ok(target)
+            if path.is_symlink()
+                && args.follow_symlinks != FollowSymlinks::Never
+                && inside(&target) =>
+        {
+            target
+        }
+        _ => path,
+    };
+    let mut info = file_info(path, &metadata, registry, args);
+    info.subdir = subdir;
+    let name = info
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    let outcome = conflict_strategy(&info, output, registry, args, asker, prompt_terminal)
+        .and_then(|conflict| {
+            move_file(
+                &info,
+                output,
+                registry,
+                journal,
+                args,
+                conflict,
+                &mut |_, _| {},
+            )
+        });
+    match outcome {
+        Ok(
+            MoveOutcome::Moved(destination)
+            | MoveOutcome::Copied(destination)
+            | MoveOutcome::Linked(destination),
+        ) => println!(
+            "  {} {} -> {}",
+            "✓".bright_green(),
+            name,
+            relative(&destination, output).bright_cyan()
+        ),
+        Ok(MoveOutcome::Skipped) => println!("  {} {} (already exists)", "⊘".bright_yellow(), name),
+        Ok(MoveOutcome::Duplicate(existing)) => println!(
+            "  {} {} (duplicate of {}{})",
+            "≡".bright_blue(),
+            name,
+            existing.display(),
+            if args.dry_run {
+                ", would be removed"
+            } else {
+                ", removed"
+            }
+        ),
+        Err(e) => println!(
+            "  {} {} - {}",
+            "✗".bright_red(),
+            info.path.display(),
+            e.to_string().bright_red()
+        ),
+    }
+}
+
+fn notify_error(error: notify::Error) -> OrganizerError {
+    match error.kind {
+        notify::ErrorKind::Io(e) => OrganizerError::IoError(e),
+        _ => OrganizerError::Watch(error.to_string()),
+    }
+}